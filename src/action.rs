@@ -0,0 +1,248 @@
+use crate::{
+    blind::Blind, card::CardId, error::GameError, shop::SaleCard, GamePhase, GameState, HandResult,
+    PlayerMoney,
+};
+
+/// Every move a player can make. Indices refer to positions in the matching
+/// list on `GameState` (shop cards, vouchers, packs, jokers, consumables, or the
+/// cards of the open pack).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GameAction {
+    // Blind select
+    SelectBlind,
+    SkipBlind,
+
+    // Playing
+    SelectCard(CardId),
+    DeselectCard(CardId),
+    PlayHand,
+    DiscardHand,
+
+    // Cash out
+    CashOut,
+
+    // Shop
+    BuyCard(usize),
+    BuyVoucher(usize),
+    OpenPack(usize),
+    PickPackCard(usize),
+    SkipPack,
+    Reroll,
+    LeaveShop,
+
+    // Any time
+    SellJoker(usize),
+    SellConsumable(usize),
+    UseConsumable(usize),
+}
+
+impl GameState {
+    /// Lists the actions that are valid right now. Anything returned here can be
+    /// passed to `apply` without getting an error back.
+    pub fn legal_actions(&self) -> Vec<GameAction> {
+        let mut actions = vec![];
+
+        if let Some(open_pack) = &self.open_pack {
+            for (idx, card) in open_pack.cards.iter().enumerate() {
+                if self.can_pick_pack_card(card) {
+                    actions.push(GameAction::PickPackCard(idx));
+                }
+            }
+
+            actions.push(GameAction::SkipPack);
+            return actions;
+        }
+
+        match self.phase {
+            GamePhase::BlindSelect => {
+                actions.push(GameAction::SelectBlind);
+
+                if !matches!(self.blind, Blind::Boss(_)) {
+                    actions.push(GameAction::SkipBlind);
+                }
+            }
+            GamePhase::Playing => {
+                if self.selected_cards.len() < self.get_selection_limit() {
+                    for card in self.in_hand.iter() {
+                        actions.push(GameAction::SelectCard(card.id));
+                    }
+                }
+
+                for card in self.selected_cards.iter() {
+                    actions.push(GameAction::DeselectCard(card.id));
+                }
+
+                let selected = self.selected_cards.len();
+                if selected > 0 && selected <= self.get_selection_limit() {
+                    if self.hands > 0 {
+                        actions.push(GameAction::PlayHand);
+                    }
+
+                    if self.discards > 0 {
+                        actions.push(GameAction::DiscardHand);
+                    }
+                }
+            }
+            GamePhase::Shop => {
+                let can_afford = |cost: u8| self.get_price(cost) <= self.money;
+
+                for (idx, card) in self.shop.cards.iter().enumerate() {
+                    let has_slot = match card {
+                        SaleCard::Joker(joker) => self.has_joker_slot(joker),
+                        SaleCard::Consumable(_) => {
+                            self.consumables.len() < self.get_consumable_slots()
+                        }
+                    };
+
+                    if has_slot && can_afford(card.get_cost()) {
+                        actions.push(GameAction::BuyCard(idx));
+                    }
+                }
+
+                for (idx, voucher) in self.shop.vouchers.iter().enumerate() {
+                    if can_afford(voucher.get_cost()) {
+                        actions.push(GameAction::BuyVoucher(idx));
+                    }
+                }
+
+                for (idx, pack) in self.shop.packs.iter().enumerate() {
+                    if can_afford(pack.get_cost()) {
+                        actions.push(GameAction::OpenPack(idx));
+                    }
+                }
+
+                if self.shop.reroll_cost as PlayerMoney <= self.money {
+                    actions.push(GameAction::Reroll);
+                }
+
+                actions.push(GameAction::LeaveShop);
+            }
+            GamePhase::CashOut => actions.push(GameAction::CashOut),
+            // Nothing left to do once the run is lost
+            GamePhase::GameOver => return actions,
+        }
+
+        for idx in 0..self.jokers.len() {
+            actions.push(GameAction::SellJoker(idx));
+        }
+
+        for idx in 0..self.consumables.len() {
+            actions.push(GameAction::SellConsumable(idx));
+        }
+
+        for (idx, consumable) in self.consumables.iter().enumerate() {
+            if self.can_use_consumable(&consumable.consumable) {
+                actions.push(GameAction::UseConsumable(idx));
+            }
+        }
+
+        actions
+    }
+
+    /// Runs any action. Only `PlayHand` can end a blind, so it's the only one
+    /// that returns a `HandResult`.
+    pub fn apply(&mut self, action: GameAction) -> Result<Option<HandResult>, GameError> {
+        match action {
+            GameAction::SelectBlind => self.select_blind()?,
+            GameAction::SkipBlind => self.skip_blind()?,
+            GameAction::SelectCard(card_id) => self.select_card(card_id)?,
+            GameAction::DeselectCard(card_id) => self.deselect_card(card_id)?,
+            GameAction::PlayHand => return self.play_hand().map(|played| played.result),
+            GameAction::DiscardHand => self.discard_hand()?,
+            GameAction::CashOut => self.collect_cash_out()?,
+            GameAction::BuyCard(idx) => self.buy_card(idx)?,
+            GameAction::BuyVoucher(idx) => self.buy_voucher(idx)?,
+            GameAction::OpenPack(idx) => self.open_pack(idx)?,
+            GameAction::PickPackCard(idx) => self.pick_pack_card(idx)?,
+            GameAction::SkipPack => self.skip_pack()?,
+            GameAction::Reroll => self.reroll_shop()?,
+            GameAction::LeaveShop => self.leave_shop()?,
+            GameAction::SellJoker(idx) => self.sell_joker(idx)?,
+            GameAction::SellConsumable(idx) => self.sell_consumable(idx)?,
+            GameAction::UseConsumable(idx) => self.use_consumable(idx)?,
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+    use super::*;
+    use crate::{new_test_game, stake::GameStake, GameStartingDeck};
+
+    #[test]
+    fn test_legal_actions_always_apply() {
+        for seed in 0..20 {
+            let mut state = new_test_game(GameStartingDeck::Magic, GameStake::White, seed);
+            state.money = 100;
+
+            let mut rng = StdRng::seed_from_u64(seed);
+            for _ in 0..500 {
+                let actions = state.legal_actions();
+                let action = actions.choose(&mut rng).unwrap().clone();
+
+                let result = state.apply(action.clone());
+                assert!(result.is_ok(), "{:?} failed: {:?}", action, result);
+
+                if result == Ok(Some(HandResult::Lose)) {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_shop_actions() {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 3);
+
+        assert_eq!(
+            state.apply(GameAction::Reroll),
+            Err(GameError::WrongPhase {
+                expected: GamePhase::Shop,
+                actual: GamePhase::BlindSelect,
+            })
+        );
+
+        state.apply(GameAction::SelectBlind).unwrap();
+        state.score = state.get_score_needed();
+        let id = state.in_hand[0].id;
+        state.apply(GameAction::SelectCard(id)).unwrap();
+        assert_eq!(state.apply(GameAction::PlayHand), Ok(Some(HandResult::Win)));
+        assert_eq!(state.legal_actions(), vec![GameAction::CashOut]);
+
+        state.apply(GameAction::CashOut).unwrap();
+        assert_eq!(state.phase, GamePhase::Shop);
+        assert_eq!(state.shop.cards.len(), 2);
+        assert_eq!(state.shop.packs.len(), 2);
+
+        state.money = 0;
+        assert!(!state.legal_actions().contains(&GameAction::Reroll));
+        assert!(matches!(
+            state.apply(GameAction::Reroll),
+            Err(GameError::NotEnoughMoney { .. })
+        ));
+
+        state.money = 20;
+        state.apply(GameAction::OpenPack(0)).unwrap();
+        assert!(state.open_pack.is_some());
+        assert_eq!(state.apply(GameAction::LeaveShop), Err(GameError::PackOpen));
+
+        state.apply(GameAction::SkipPack).unwrap();
+        state.apply(GameAction::LeaveShop).unwrap();
+        assert_eq!(state.phase, GamePhase::BlindSelect);
+        assert_eq!(state.blind, Blind::Big);
+
+        state.apply(GameAction::SkipBlind).unwrap();
+        assert!(matches!(state.blind, Blind::Boss(_)));
+        assert_eq!(
+            state.apply(GameAction::SkipBlind),
+            Err(GameError::CannotSkipBlind)
+        );
+
+        state.apply(GameAction::SelectBlind).unwrap();
+        assert_eq!(state.phase, GamePhase::Playing);
+    }
+}
@@ -1,28 +1,81 @@
-use crate::stake::GameStake;
-
-pub fn get_base_score(ante: u8, stake: &GameStake) -> u32 {
-    let base_score: (u32, u32, u32) = match ante {
-        0 => (100, 100, 100),
-        1 => (300, 300, 300),
-        2 => (800, 900, 1000),
-        3 => (2000, 2600, 3200),
-        4 => (5000, 8000, 9000),
-        5 => (11000, 20000, 25000),
-        6 => (20000, 36000, 60000),
-        7 => (35000, 60000, 110000),
-        8 => (50000, 100000, 200000),
-
-        _ => (0, 0, 0),
+use crate::{score::Score, stake::GameStake};
+
+// Beating the boss of this ante wins the run; after that it's endless mode
+pub const WINNING_ANTE: u8 = 8;
+
+pub fn get_base_score(ante: u8, stake: &GameStake) -> Score {
+    let base_scores: [u32; 8] = match stake {
+        GameStake::White | GameStake::Red => [300, 800, 2000, 5000, 11000, 20000, 35000, 50000],
+        GameStake::Green | GameStake::Black | GameStake::Blue => {
+            [300, 900, 2600, 8000, 20000, 36000, 60000, 100000]
+        }
+        GameStake::Purple | GameStake::Orange | GameStake::Gold => {
+            [300, 1000, 3200, 9000, 25000, 60000, 110000, 200000]
+        }
+    };
+
+    match ante {
+        0 => Score::from(100),
+        1..=WINNING_ANTE => Score::from(base_scores[ante as usize - 1]),
+        _ => get_endless_score(ante, base_scores[7]),
+    }
+}
+
+// The game's formula past ante 8: a * (1.6 + (0.75 * c) ^ (1 + 0.2 * c)) ^ c,
+// cut down to two significant figures. Worked out in log10 since it passes
+// f64::MAX within a few dozen antes.
+fn get_endless_score(ante: u8, last_score: u32) -> Score {
+    let c = (ante - WINNING_ANTE) as f64;
+    let d = 1.0 + 0.2 * c;
+
+    let growth_log = d * (0.75 * c).log10();
+    let base_log = if growth_log > 15.0 {
+        // 1.6 no longer changes anything an f64 can hold
+        growth_log
+    } else {
+        (1.6 + 10f64.powf(growth_log)).log10()
     };
 
-    match stake {
-        GameStake::White => base_score.0,
-        GameStake::Red => base_score.0,
-        GameStake::Green => base_score.1,
-        GameStake::Black => base_score.1,
-        GameStake::Blue => base_score.1,
-        GameStake::Purple => base_score.2,
-        GameStake::Orange => base_score.2,
-        GameStake::Gold => base_score.2,
+    let log = (last_score as f64).log10() + c * base_log;
+    let exponent = log.floor();
+    let leading = (10f64.powf(log - exponent) * 10.0).floor();
+
+    Score::new(leading, exponent as i64 - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_score() {
+        assert_eq!(get_base_score(1, &GameStake::White), Score::from(300));
+        assert_eq!(get_base_score(8, &GameStake::Gold), Score::from(200000));
+        assert_eq!(get_base_score(0, &GameStake::Red), Score::from(100));
+    }
+
+    #[test]
+    fn test_endless_score() {
+        let endless = [
+            (9, "110000"),
+            (10, "560000"),
+            (11, "7200000"),
+            (12, "300000000"),
+            (13, "47000000000"),
+            (14, "2.9e13"),
+            (16, "8.6e20"),
+        ];
+
+        for (ante, score) in endless {
+            assert_eq!(get_base_score(ante, &GameStake::White).to_string(), score);
+        }
+
+        // Grows forever instead of wrapping
+        let mut last = get_base_score(WINNING_ANTE, &GameStake::White);
+        for ante in WINNING_ANTE + 1..=u8::MAX {
+            let score = get_base_score(ante, &GameStake::White);
+            assert!(score > last, "ante {}", ante);
+            last = score;
+        }
     }
 }
@@ -1,8 +1,9 @@
-use core::fmt;
 use std::fmt::Debug;
 
 use colored::Colorize;
-use rand::{seq::SliceRandom, thread_rng};
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{ante::WINNING_ANTE, score::Score};
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum BossBlind {
@@ -40,21 +41,19 @@ pub enum BossBlind {
 }
 
 impl BossBlind {
-    pub fn get_rand(ante: u8) -> Self {
-        let mut rng = thread_rng();
-
-        if ante % 8 == 0 {
-            return FINISHER_BLINDS.choose(&mut rng).unwrap().clone();
+    pub fn get_rand(ante: u8, rng: &mut impl Rng) -> Self {
+        // Antes 8, 16, 24, ... end on a finisher, including in endless mode
+        if ante > 0 && ante.is_multiple_of(WINNING_ANTE) {
+            return FINISHER_BLINDS.choose(rng).unwrap().clone();
         }
 
-        return BOSS_BLINDS
+        BOSS_BLINDS
             .iter()
             .filter(|blind| blind.get_min_ante() <= ante)
             .collect::<Vec<_>>()
-            .choose(&mut rng)
+            .choose(rng)
+            .map(|blind| (*blind).clone())
             .unwrap()
-            .clone()
-            .clone();
     }
 
     pub fn get_reward(&self) -> u32 {
@@ -91,37 +90,37 @@ impl BossBlind {
         }
     }
 
-    pub fn get_min_score(&self, base_score: u32) -> u32 {
+    pub fn get_min_score(&self, base_score: Score) -> Score {
         match self {
-            BossBlind::Hook => base_score * 2,
-            BossBlind::Ox => base_score * 2,
-            BossBlind::House => base_score * 2,
-            BossBlind::Wall => base_score * 4, // x4
-            BossBlind::Wheel => base_score * 2,
-            BossBlind::Arm => base_score * 2,
-            BossBlind::Club => base_score * 2,
-            BossBlind::Fish => base_score * 2,
-            BossBlind::Psychic => base_score * 2,
-            BossBlind::Goad => base_score * 2,
-            BossBlind::Water => base_score * 2,
-            BossBlind::Window => base_score * 2,
-            BossBlind::Manacle => base_score * 2,
-            BossBlind::Eye => base_score * 2,
-            BossBlind::Mouth => base_score * 2,
-            BossBlind::Plant => base_score * 2,
-            BossBlind::Serpent => base_score * 2,
-            BossBlind::Pillar => base_score * 2,
-            BossBlind::Needle => base_score * 1, // x1
-            BossBlind::Head => base_score * 2,
-            BossBlind::Tooth => base_score * 2,
-            BossBlind::Flint => base_score * 2,
-            BossBlind::Mark => base_score * 2,
-
-            BossBlind::AmberAcorn => base_score * 2,
-            BossBlind::VerdantLeaf => base_score * 2,
-            BossBlind::VioletVessel => base_score * 6, // x6
-            BossBlind::CrimsonHeart => base_score * 2,
-            BossBlind::CeruleanBell => base_score * 2,
+            BossBlind::Hook => base_score * 2.0,
+            BossBlind::Ox => base_score * 2.0,
+            BossBlind::House => base_score * 2.0,
+            BossBlind::Wall => base_score * 4.0, // x4
+            BossBlind::Wheel => base_score * 2.0,
+            BossBlind::Arm => base_score * 2.0,
+            BossBlind::Club => base_score * 2.0,
+            BossBlind::Fish => base_score * 2.0,
+            BossBlind::Psychic => base_score * 2.0,
+            BossBlind::Goad => base_score * 2.0,
+            BossBlind::Water => base_score * 2.0,
+            BossBlind::Window => base_score * 2.0,
+            BossBlind::Manacle => base_score * 2.0,
+            BossBlind::Eye => base_score * 2.0,
+            BossBlind::Mouth => base_score * 2.0,
+            BossBlind::Plant => base_score * 2.0,
+            BossBlind::Serpent => base_score * 2.0,
+            BossBlind::Pillar => base_score * 2.0,
+            BossBlind::Needle => base_score, // x1
+            BossBlind::Head => base_score * 2.0,
+            BossBlind::Tooth => base_score * 2.0,
+            BossBlind::Flint => base_score * 2.0,
+            BossBlind::Mark => base_score * 2.0,
+
+            BossBlind::AmberAcorn => base_score * 2.0,
+            BossBlind::VerdantLeaf => base_score * 2.0,
+            BossBlind::VioletVessel => base_score * 6.0, // x6
+            BossBlind::CrimsonHeart => base_score * 2.0,
+            BossBlind::CeruleanBell => base_score * 2.0,
         }
     }
 
@@ -210,13 +209,10 @@ impl Blind {
         }
     }
 
-    pub fn get_min_score(&self, base_score: u32) -> u32 {
+    pub fn get_min_score(&self, base_score: Score) -> Score {
         match self {
-            Blind::Small => base_score * 1,
-            Blind::Big => {
-                let a = base_score as f32 * 1.5;
-                return a.ceil() as u32;
-            }
+            Blind::Small => base_score,
+            Blind::Big => (base_score * 1.5).ceil(),
             Blind::Boss(boss) => boss.get_min_score(base_score),
         }
     }
@@ -241,3 +237,21 @@ impl Debug for Blind {
         write!(f, "{}", txt)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_finisher_every_eighth_ante() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for ante in 0..=40 {
+            let boss = BossBlind::get_rand(ante, &mut rng);
+            let is_finisher = FINISHER_BLINDS.contains(&boss);
+            assert_eq!(is_finisher, ante > 0 && ante % 8 == 0, "ante {}", ante);
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{card::Card, consumable::Consumable, joker::JokerCard};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BoosterPack {
+    Standard,
+    StandardJumbo,
+    StandardMega,
+
+    Arcana,
+    JumboArcana,
+    MegaArcana,
+
+    Celestial,
+    JumboCelestial,
+    MegaCelestial,
+
+    Buffoon,
+    JumboBuffoon,
+    MegaBuffoon,
+
+    Spectral,
+    JumboSpectral,
+    MegaSpectral,
+}
+
+pub const BOOSTER_PACKS: [BoosterPack; 15] = [
+    BoosterPack::Standard,
+    BoosterPack::StandardJumbo,
+    BoosterPack::StandardMega,
+    BoosterPack::Arcana,
+    BoosterPack::JumboArcana,
+    BoosterPack::MegaArcana,
+    BoosterPack::Celestial,
+    BoosterPack::JumboCelestial,
+    BoosterPack::MegaCelestial,
+    BoosterPack::Buffoon,
+    BoosterPack::JumboBuffoon,
+    BoosterPack::MegaBuffoon,
+    BoosterPack::Spectral,
+    BoosterPack::JumboSpectral,
+    BoosterPack::MegaSpectral,
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BoosterPackKind {
+    Standard,
+    Arcana,
+    Celestial,
+    Buffoon,
+    Spectral,
+}
+
+impl BoosterPack {
+    pub fn get_rand(rng: &mut impl Rng) -> Self {
+        BOOSTER_PACKS
+            .choose_weighted(rng, |pack| pack.get_weight())
+            .unwrap()
+            .clone()
+    }
+
+    pub fn get_kind(&self) -> BoosterPackKind {
+        match self {
+            BoosterPack::Standard | BoosterPack::StandardJumbo | BoosterPack::StandardMega => {
+                BoosterPackKind::Standard
+            }
+            BoosterPack::Arcana | BoosterPack::JumboArcana | BoosterPack::MegaArcana => {
+                BoosterPackKind::Arcana
+            }
+            BoosterPack::Celestial | BoosterPack::JumboCelestial | BoosterPack::MegaCelestial => {
+                BoosterPackKind::Celestial
+            }
+            BoosterPack::Buffoon | BoosterPack::JumboBuffoon | BoosterPack::MegaBuffoon => {
+                BoosterPackKind::Buffoon
+            }
+            BoosterPack::Spectral | BoosterPack::JumboSpectral | BoosterPack::MegaSpectral => {
+                BoosterPackKind::Spectral
+            }
+        }
+    }
+
+    pub fn get_cost(&self) -> u8 {
+        match self {
+            BoosterPack::Standard => 4,
+            BoosterPack::StandardJumbo => 6,
+            BoosterPack::StandardMega => 8,
+
+            BoosterPack::Arcana => 4,
+            BoosterPack::JumboArcana => 6,
+            BoosterPack::MegaArcana => 8,
+
+            BoosterPack::Celestial => 4,
+            BoosterPack::JumboCelestial => 6,
+            BoosterPack::MegaCelestial => 8,
+
+            BoosterPack::Buffoon => 4,
+            BoosterPack::JumboBuffoon => 6,
+            BoosterPack::MegaBuffoon => 8,
+
+            BoosterPack::Spectral => 4,
+            BoosterPack::JumboSpectral => 6,
+            BoosterPack::MegaSpectral => 8,
+        }
+    }
+
+    pub fn get_weight(&self) -> f32 {
+        match self {
+            BoosterPack::Standard => 4.0,
+            BoosterPack::StandardJumbo => 2.0,
+            BoosterPack::StandardMega => 0.5,
+
+            BoosterPack::Arcana => 4.0,
+            BoosterPack::JumboArcana => 2.0,
+            BoosterPack::MegaArcana => 0.5,
+
+            BoosterPack::Celestial => 4.0,
+            BoosterPack::JumboCelestial => 2.0,
+            BoosterPack::MegaCelestial => 0.5,
+
+            BoosterPack::Buffoon => 1.2,
+            BoosterPack::JumboBuffoon => 0.6,
+            BoosterPack::MegaBuffoon => 0.15,
+
+            BoosterPack::Spectral => 0.6,
+            BoosterPack::JumboSpectral => 0.3,
+            BoosterPack::MegaSpectral => 0.07,
+        }
+    }
+
+    // Number of cards shown when the pack is opened
+    pub fn get_size(&self) -> u8 {
+        match self {
+            BoosterPack::Standard => 3,
+            BoosterPack::StandardJumbo => 5,
+            BoosterPack::StandardMega => 5,
+
+            BoosterPack::Arcana => 3,
+            BoosterPack::JumboArcana => 5,
+            BoosterPack::MegaArcana => 5,
+
+            BoosterPack::Celestial => 3,
+            BoosterPack::JumboCelestial => 5,
+            BoosterPack::MegaCelestial => 5,
+
+            BoosterPack::Buffoon => 2,
+            BoosterPack::JumboBuffoon => 4,
+            BoosterPack::MegaBuffoon => 4,
+
+            BoosterPack::Spectral => 2,
+            BoosterPack::JumboSpectral => 4,
+            BoosterPack::MegaSpectral => 4,
+        }
+    }
+
+    // Number of cards the player may take from the pack
+    pub fn get_choices(&self) -> u8 {
+        match self {
+            BoosterPack::StandardMega
+            | BoosterPack::MegaArcana
+            | BoosterPack::MegaCelestial
+            | BoosterPack::MegaBuffoon
+            | BoosterPack::MegaSpectral => 2,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum PackCard {
+    Card(Card),
+    Joker(JokerCard),
+    Consumable(Consumable),
+}
+
+/// A booster pack that has been bought and is waiting for the player to pick from it.
+#[derive(Clone, Debug)]
+pub struct OpenPack {
+    pub pack: BoosterPack,
+    pub cards: Vec<PackCard>,
+    pub choices_left: u8,
+}
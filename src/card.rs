@@ -1,11 +1,8 @@
-use std::{
-    fmt::Debug,
-    sync::atomic::{AtomicU32, Ordering},
-};
+use std::fmt::Debug;
 
 use colored::Colorize;
 use itertools::Itertools;
-use rand::seq::SliceRandom;
+use rand::{seq::SliceRandom, Rng};
 
 #[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum CardSuit {
@@ -35,12 +32,16 @@ pub const CARD_SUITS: [CardSuit; 4] = [
 ];
 
 impl CardSuit {
-    pub fn get_rand() -> Self {
-        CARD_SUITS.choose(&mut rand::thread_rng()).unwrap().clone()
+    pub fn get_rand(rng: &mut impl Rng) -> Self {
+        CARD_SUITS.choose(rng).unwrap().clone()
+    }
+
+    pub fn is_red(&self) -> bool {
+        matches!(self, CardSuit::Hearts | CardSuit::Diamonds)
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum CardEnhancement {
     Bonus,
     Mult,
@@ -52,7 +53,24 @@ pub enum CardEnhancement {
     Lucky,
 }
 
-#[derive(Clone, Debug)]
+pub const CARD_ENHANCEMENTS: [CardEnhancement; 8] = [
+    CardEnhancement::Bonus,
+    CardEnhancement::Mult,
+    CardEnhancement::Wild,
+    CardEnhancement::Glass,
+    CardEnhancement::Steel,
+    CardEnhancement::Stone,
+    CardEnhancement::Gold,
+    CardEnhancement::Lucky,
+];
+
+impl CardEnhancement {
+    pub fn get_rand(rng: &mut impl Rng) -> Self {
+        CARD_ENHANCEMENTS.choose(rng).unwrap().clone()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum CardEdition {
     Base,
     Foil,
@@ -61,6 +79,38 @@ pub enum CardEdition {
     Negative,
 }
 
+impl CardEdition {
+    // Foil, Holographic or Polychrome, as rolled by Aura and Wheel of Fortune
+    pub fn get_rand(rng: &mut impl Rng) -> Self {
+        [
+            (CardEdition::Foil, 50),
+            (CardEdition::Holographic, 35),
+            (CardEdition::Polychrome, 15),
+        ]
+        .choose_weighted(rng, |(_, weight)| *weight)
+        .unwrap()
+        .0
+        .clone()
+    }
+
+    // Edition for a shop joker or Standard pack card, if it gets one. `rate` is
+    // raised by Hone and Glow Up, and scales every edition but Negative
+    pub fn roll(rng: &mut impl Rng, rate: f64, allow_negative: bool) -> Option<Self> {
+        let poll = rng.gen::<f64>();
+        if allow_negative && poll > 1.0 - 0.003 {
+            Some(CardEdition::Negative)
+        } else if poll > 1.0 - 0.006 * rate {
+            Some(CardEdition::Polychrome)
+        } else if poll > 1.0 - 0.02 * rate {
+            Some(CardEdition::Holographic)
+        } else if poll > 1.0 - 0.04 * rate {
+            Some(CardEdition::Foil)
+        } else {
+            None
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum CardSeal {
     Gold,
@@ -69,6 +119,19 @@ pub enum CardSeal {
     Purple,
 }
 
+pub const CARD_SEALS: [CardSeal; 4] = [
+    CardSeal::Gold,
+    CardSeal::Red,
+    CardSeal::Blue,
+    CardSeal::Purple,
+];
+
+impl CardSeal {
+    pub fn get_rand(rng: &mut impl Rng) -> Self {
+        CARD_SEALS.choose(rng).unwrap().clone()
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum CardRank {
     Two,
@@ -124,8 +187,8 @@ pub const CARD_RANKS: [CardRank; 13] = [
 ];
 
 impl CardRank {
-    pub fn get_rand() -> Self {
-        CARD_RANKS.choose(&mut rand::thread_rng()).unwrap().clone()
+    pub fn get_rand(rng: &mut impl Rng) -> Self {
+        CARD_RANKS.choose(rng).unwrap().clone()
     }
 
     pub fn get_straight_next(&self) -> Self {
@@ -187,7 +250,7 @@ impl CardRank {
 
 pub const FACE_CARDS: [CardRank; 3] = [CardRank::Jack, CardRank::Queen, CardRank::King];
 
-type CardId = u32;
+pub type CardId = u32;
 
 #[derive(Clone)]
 pub struct Card {
@@ -200,29 +263,84 @@ pub struct Card {
     pub seal: Option<CardSeal>,
 
     pub extra_chips: u32,
+
+    // Set by the boss blind while it is played, and cleared when the round ends
+    pub is_debuffed: bool,
+    pub is_face_down: bool,
 }
 
 impl Card {
-    fn get_next_id() -> CardId {
-        static NEXT_ID: AtomicU32 = AtomicU32::new(0);
-        NEXT_ID.fetch_add(1, Ordering::Relaxed)
-    }
-
-    pub fn new(rank: CardRank, suit: CardSuit) -> Self {
+    pub fn new(id: CardId, rank: CardRank, suit: CardSuit) -> Self {
         Self {
-            id: Self::get_next_id(),
+            id,
             rank,
             suit,
             enhancement: None,
             edition: None,
             seal: None,
             extra_chips: 0,
+            is_debuffed: false,
+            is_face_down: false,
+        }
+    }
+
+    // Stone cards have no rank or suit
+    pub fn has_rank(&self) -> bool {
+        self.enhancement != Some(CardEnhancement::Stone)
+    }
+
+    // What the player can see of the card; a face down card shows neither
+    pub fn get_visible_rank(&self) -> Option<&CardRank> {
+        (!self.is_face_down).then_some(&self.rank)
+    }
+
+    pub fn get_visible_suit(&self) -> Option<&CardSuit> {
+        (!self.is_face_down).then_some(&self.suit)
+    }
+
+    pub(crate) fn clear_round_status(&mut self) {
+        self.is_debuffed = false;
+        self.is_face_down = false;
+    }
+}
+
+/// Hands out card IDs for a single game, so a fresh game always numbers its
+/// cards the same way no matter what else the process has done.
+#[derive(Clone, Debug, Default)]
+pub struct CardIdAllocator {
+    next_id: CardId,
+}
+
+impl CardIdAllocator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn next_id(&mut self) -> CardId {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    pub fn new_card(&mut self, rank: CardRank, suit: CardSuit) -> Card {
+        Card::new(self.next_id(), rank, suit)
+    }
+
+    // Exact copy of a card under a new ID, e.g. for Cryptid or DNA
+    pub fn copy_card(&mut self, card: &Card) -> Card {
+        Card {
+            id: self.next_id(),
+            ..card.clone()
         }
     }
 }
 
 impl Debug for Card {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_face_down {
+            return write!(f, "{}", "[?]".dimmed());
+        }
+
         let txt = format!("[{:?} {:?}]", self.rank, self.suit);
         let txt = match self.suit {
             CardSuit::Spades => txt.black(),
@@ -234,12 +352,64 @@ impl Debug for Card {
     }
 }
 
+// Sorted by what can be seen, so face down cards don't give themselves away
 pub fn debug_cards(cards: &[Card]) -> String {
     cards
         .iter()
-        .sorted_by(|a, b| a.suit.cmp(&b.suit))
-        .sorted_by(|a, b| b.rank.cmp(&a.rank))
+        .sorted_by(|a, b| a.get_visible_suit().cmp(&b.get_visible_suit()))
+        .sorted_by(|a, b| b.get_visible_rank().cmp(&a.get_visible_rank()))
         .map(|card| format!("{:?}", card))
         .collect::<Vec<String>>()
         .join(", ")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn count_editions(rate: f64, allow_negative: bool) -> [usize; 5] {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut counts = [0; 5];
+        for _ in 0..100_000 {
+            let idx = match CardEdition::roll(&mut rng, rate, allow_negative) {
+                None | Some(CardEdition::Base) => 0,
+                Some(CardEdition::Foil) => 1,
+                Some(CardEdition::Holographic) => 2,
+                Some(CardEdition::Polychrome) => 3,
+                Some(CardEdition::Negative) => 4,
+            };
+            counts[idx] += 1;
+        }
+        counts
+    }
+
+    fn assert_near(count: usize, expected: usize) {
+        let tolerance = expected / 10;
+        assert!(
+            count.abs_diff(expected) <= tolerance,
+            "{} is not within {} of {}",
+            count,
+            tolerance,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_edition_roll_odds() {
+        // Out of 100,000: Foil 2%, Holographic 1.4%, Polychrome 0.3% and
+        // Negative 0.3%, which takes the top of Polychrome's range
+        let counts = count_editions(1.0, true);
+        assert_near(counts[1], 2000);
+        assert_near(counts[2], 1400);
+        assert_near(counts[3], 300);
+        assert_near(counts[4], 300);
+
+        // The rate scales everything but Negative
+        let counts = count_editions(2.0, false);
+        assert_near(counts[1], 4000);
+        assert_near(counts[2], 2800);
+        assert_near(counts[3], 1200);
+        assert_eq!(counts[4], 0);
+    }
+}
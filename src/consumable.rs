@@ -1,4 +1,17 @@
-#[derive(Clone, Debug)]
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    card::{
+        Card, CardEdition, CardEnhancement, CardRank, CardSeal, CardSuit, CARD_RANKS, FACE_CARDS,
+    },
+    error::GameError,
+    hand::{HandType, HAND_TYPES},
+    joker::{JokerCard, JokerRarity, JokerType, JOKERS},
+    rng::RngStream,
+    GamePhase, GameState,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum TarotCard {
     TheFool,
     TheMagician,
@@ -24,7 +37,67 @@ pub enum TarotCard {
     TheWorld,
 }
 
-#[derive(Clone, Debug)]
+pub const TAROT_CARDS: [TarotCard; 22] = [
+    TarotCard::TheFool,
+    TarotCard::TheMagician,
+    TarotCard::TheHighPriestess,
+    TarotCard::TheEmpress,
+    TarotCard::TheEmperor,
+    TarotCard::TheHierophant,
+    TarotCard::TheLovers,
+    TarotCard::TheChariot,
+    TarotCard::Justice,
+    TarotCard::TheHermit,
+    TarotCard::WheelOfFortune,
+    TarotCard::Strength,
+    TarotCard::TheHangedMan,
+    TarotCard::Death,
+    TarotCard::Temperance,
+    TarotCard::TheDevil,
+    TarotCard::TheTower,
+    TarotCard::TheStar,
+    TarotCard::TheMoon,
+    TarotCard::TheSun,
+    TarotCard::Judgement,
+    TarotCard::TheWorld,
+];
+
+impl TarotCard {
+    pub fn get_rand(rng: &mut impl Rng) -> Self {
+        TAROT_CARDS.choose(rng).unwrap().clone()
+    }
+
+    // Most cards in hand this tarot can be used on; 0 if it doesn't target cards
+    pub fn get_max_targets(&self) -> usize {
+        match self {
+            TarotCard::TheMagician => 2,
+            TarotCard::TheEmpress => 2,
+            TarotCard::TheHierophant => 2,
+            TarotCard::TheLovers => 1,
+            TarotCard::TheChariot => 1,
+            TarotCard::Justice => 1,
+            TarotCard::Strength => 2,
+            TarotCard::TheHangedMan => 2,
+            TarotCard::Death => 2,
+            TarotCard::TheDevil => 1,
+            TarotCard::TheTower => 1,
+            TarotCard::TheStar => 3,
+            TarotCard::TheMoon => 3,
+            TarotCard::TheSun => 3,
+            TarotCard::TheWorld => 3,
+            _ => 0,
+        }
+    }
+
+    pub fn get_min_targets(&self) -> usize {
+        match self {
+            TarotCard::Death => 2,
+            _ => self.get_max_targets().min(1),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum PlanetCard {
     Pluto,
     Mercury,
@@ -42,7 +115,69 @@ pub enum PlanetCard {
     Eris,
 }
 
-#[derive(Clone, Debug)]
+pub const PLANET_CARDS: [PlanetCard; 12] = [
+    PlanetCard::Pluto,
+    PlanetCard::Mercury,
+    PlanetCard::Uranus,
+    PlanetCard::Venus,
+    PlanetCard::Saturn,
+    PlanetCard::Jupiter,
+    PlanetCard::Earth,
+    PlanetCard::Mars,
+    PlanetCard::Neptune,
+    PlanetCard::PlanetX,
+    PlanetCard::Ceres,
+    PlanetCard::Eris,
+];
+
+impl PlanetCard {
+    pub fn get_rand(rng: &mut impl Rng) -> Self {
+        PLANET_CARDS
+            .iter()
+            .filter(|planet| !planet.is_secret())
+            .collect::<Vec<_>>()
+            .choose(rng)
+            .map(|planet| (*planet).clone())
+            .unwrap()
+    }
+
+    pub fn is_secret(&self) -> bool {
+        matches!(
+            self,
+            PlanetCard::PlanetX | PlanetCard::Ceres | PlanetCard::Eris
+        )
+    }
+
+    // The planet that levels up this hand
+    pub fn from_hand_type(hand_type: &HandType) -> Self {
+        let level_type = hand_type.get_level_type();
+        PLANET_CARDS
+            .iter()
+            .find(|planet| planet.get_hand_type() == level_type)
+            .unwrap()
+            .clone()
+    }
+
+    pub fn get_hand_type(&self) -> HandType {
+        match self {
+            PlanetCard::Pluto => HandType::HighCard,
+            PlanetCard::Mercury => HandType::Pair,
+            PlanetCard::Uranus => HandType::TwoPair,
+            PlanetCard::Venus => HandType::ThreeOfAKind,
+            PlanetCard::Saturn => HandType::Straight,
+            PlanetCard::Jupiter => HandType::Flush,
+            PlanetCard::Earth => HandType::FullHouse,
+            PlanetCard::Mars => HandType::FourOfAKind,
+            PlanetCard::Neptune => HandType::StraightFlush,
+
+            PlanetCard::PlanetX => HandType::FiveOfAKind,
+            PlanetCard::Ceres => HandType::FlushHouse,
+            PlanetCard::Eris => HandType::FlushFive,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum SpectralCard {
     Familiar,
     Grim,
@@ -64,9 +199,789 @@ pub enum SpectralCard {
     BlackHole,
 }
 
-#[derive(Clone, Debug)]
+// Soul and Black Hole only show up as rare replacements, so they aren't in the pool
+pub const SPECTRAL_CARDS: [SpectralCard; 16] = [
+    SpectralCard::Familiar,
+    SpectralCard::Grim,
+    SpectralCard::Incantation,
+    SpectralCard::Talisman,
+    SpectralCard::Aura,
+    SpectralCard::Wraith,
+    SpectralCard::Sigil,
+    SpectralCard::Ouija,
+    SpectralCard::Ectoplasm,
+    SpectralCard::Immolate,
+    SpectralCard::Ankh,
+    SpectralCard::DejaVu,
+    SpectralCard::Hex,
+    SpectralCard::Trance,
+    SpectralCard::Medium,
+    SpectralCard::Cryptid,
+];
+
+impl SpectralCard {
+    pub fn get_rand(rng: &mut impl Rng) -> Self {
+        SPECTRAL_CARDS.choose(rng).unwrap().clone()
+    }
+
+    // Cards in hand this spectral must be used on; 0 if it doesn't target cards
+    pub fn get_targets(&self) -> usize {
+        match self {
+            SpectralCard::Talisman
+            | SpectralCard::Aura
+            | SpectralCard::DejaVu
+            | SpectralCard::Trance
+            | SpectralCard::Medium
+            | SpectralCard::Cryptid => 1,
+            _ => 0,
+        }
+    }
+
+    // Acts on the whole hand, so it can only be used while a blind is being played
+    pub fn needs_hand(&self) -> bool {
+        matches!(
+            self,
+            SpectralCard::Familiar
+                | SpectralCard::Grim
+                | SpectralCard::Incantation
+                | SpectralCard::Sigil
+                | SpectralCard::Ouija
+                | SpectralCard::Immolate
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Consumable {
     Tarot(TarotCard),
     Planet(PlanetCard),
     Spectral(SpectralCard),
 }
+
+impl Consumable {
+    pub fn get_cost(&self) -> u8 {
+        match self {
+            Consumable::Tarot(_) => 3,
+            Consumable::Planet(_) => 3,
+            Consumable::Spectral(_) => 4,
+        }
+    }
+
+    pub fn get_sell_value(&self) -> u8 {
+        (self.get_cost() / 2).max(1)
+    }
+}
+
+/// A consumable held by the player. A Negative one takes no consumable slot.
+#[derive(Clone, Debug)]
+pub struct ConsumableCard {
+    pub consumable: Consumable,
+    pub edition: CardEdition,
+}
+
+impl ConsumableCard {
+    pub fn new(consumable: Consumable) -> Self {
+        Self {
+            consumable,
+            edition: CardEdition::Base,
+        }
+    }
+}
+
+impl GameState {
+    pub fn can_use_consumable(&self, consumable: &Consumable) -> bool {
+        let playing = self.phase == GamePhase::Playing;
+        let selected = self.selected_cards.len();
+        let free_joker_slot = self.jokers.len() < self.get_joker_slots();
+        let plain_joker = self
+            .jokers
+            .iter()
+            .any(|joker| joker.edition == CardEdition::Base);
+
+        match consumable {
+            Consumable::Planet(_) => true,
+            Consumable::Tarot(tarot) => match tarot {
+                TarotCard::TheFool => matches!(
+                    self.last_consumable,
+                    Some(Consumable::Planet(_)) | Some(Consumable::Tarot(_))
+                ),
+                TarotCard::WheelOfFortune => plain_joker,
+                TarotCard::Judgement => free_joker_slot,
+                _ if tarot.get_max_targets() > 0 => {
+                    playing
+                        && selected >= tarot.get_min_targets()
+                        && selected <= tarot.get_max_targets()
+                }
+                _ => true,
+            },
+            Consumable::Spectral(spectral) => match spectral {
+                SpectralCard::Wraith => free_joker_slot,
+                SpectralCard::Soul => free_joker_slot && !self.get_unowned_legendaries().is_empty(),
+                SpectralCard::Ectoplasm | SpectralCard::Hex => plain_joker,
+                SpectralCard::Ankh => !self.jokers.is_empty(),
+                _ if spectral.get_targets() > 0 => playing && selected == spectral.get_targets(),
+                _ if spectral.needs_hand() => playing && !self.in_hand.is_empty(),
+                _ => true,
+            },
+        }
+    }
+
+    pub fn use_consumable(&mut self, index: usize) -> Result<(), GameError> {
+        self.expect_running()?;
+
+        let consumable = self
+            .consumables
+            .get(index)
+            .ok_or(GameError::InvalidIndex(index))?
+            .consumable
+            .clone();
+
+        if !self.can_use_consumable(&consumable) {
+            return Err(GameError::CannotUseConsumable);
+        }
+
+        self.consumables.remove(index);
+
+        match &consumable {
+            Consumable::Planet(planet) => self.level_up_hand(planet.get_hand_type()),
+            Consumable::Tarot(tarot) => {
+                self.use_tarot(tarot);
+                self.total_tarot_played += 1;
+            }
+            Consumable::Spectral(spectral) => self.use_spectral(spectral),
+        }
+
+        // The Fool copies the last tarot or planet used, but never itself
+        if consumable != Consumable::Tarot(TarotCard::TheFool) {
+            self.last_consumable = Some(consumable);
+        }
+
+        // Targets go back to their place in the hand once the consumable is spent
+        self.in_hand.append(&mut self.selected_cards);
+        self.sort_hand();
+        self.update_debuffs();
+        self.check_cards();
+        Ok(())
+    }
+
+    // Secret hands show up once they have been played
+    pub fn is_hand_visible(&self, hand_type: &HandType) -> bool {
+        !hand_type.is_secret() || self.get_hand_stats(hand_type).played > 0
+    }
+
+    // Hands in the order the game lists them
+    pub fn get_visible_hands(&self) -> Vec<HandType> {
+        HAND_TYPES
+            .iter()
+            .filter(|hand_type| self.is_hand_visible(hand_type))
+            .cloned()
+            .collect()
+    }
+
+    // Planets for secret hands only show up once that hand has been played
+    pub(crate) fn roll_planet(&mut self, stream: RngStream) -> PlanetCard {
+        let planets = PLANET_CARDS
+            .iter()
+            .filter(|planet| self.is_hand_visible(&planet.get_hand_type()))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        planets.choose(self.rng.stream(stream)).unwrap().clone()
+    }
+
+    pub fn level_up_hand(&mut self, hand_type: HandType) {
+        let stats = self
+            .hand_stats
+            .get_mut(&hand_type.get_level_type())
+            .unwrap();
+        stats.level = stats.level.saturating_add(1);
+    }
+
+    // Each Negative joker or consumable held adds a slot of its kind
+    pub fn get_joker_slots(&self) -> usize {
+        let negative = self
+            .jokers
+            .iter()
+            .filter(|joker| joker.edition == CardEdition::Negative)
+            .count();
+        self.joker_slots as usize + negative
+    }
+
+    pub fn get_consumable_slots(&self) -> usize {
+        let negative = self
+            .consumables
+            .iter()
+            .filter(|consumable| consumable.edition == CardEdition::Negative)
+            .count();
+        self.consumable_slots as usize + negative
+    }
+
+    // A Negative joker brings its own slot, so it always fits
+    pub fn has_joker_slot(&self, joker: &JokerCard) -> bool {
+        joker.edition == CardEdition::Negative || self.jokers.len() < self.get_joker_slots()
+    }
+
+    pub fn has_consumable_slot(&self, consumable: &ConsumableCard) -> bool {
+        consumable.edition == CardEdition::Negative
+            || self.consumables.len() < self.get_consumable_slots()
+    }
+
+    pub fn add_consumable(&mut self, consumable: Consumable) -> Result<(), GameError> {
+        self.add_consumable_card(ConsumableCard::new(consumable))
+    }
+
+    pub fn add_consumable_card(&mut self, consumable: ConsumableCard) -> Result<(), GameError> {
+        if !self.has_consumable_slot(&consumable) {
+            return Err(GameError::ConsumableSlotsFull);
+        }
+
+        self.consumables.push(consumable);
+        Ok(())
+    }
+
+    pub fn add_joker(&mut self, joker: JokerCard) -> Result<(), GameError> {
+        if !self.has_joker_slot(&joker) {
+            return Err(GameError::JokerSlotsFull);
+        }
+
+        self.jokers.push(joker);
+        Ok(())
+    }
+
+    fn set_selected_enhancement(&mut self, enhancement: CardEnhancement) {
+        for card in self.selected_cards.iter_mut() {
+            card.enhancement = Some(enhancement.clone());
+        }
+    }
+
+    fn set_selected_suit(&mut self, suit: CardSuit) {
+        for card in self.selected_cards.iter_mut() {
+            card.suit = suit.clone();
+        }
+    }
+
+    fn set_selected_seal(&mut self, seal: CardSeal) {
+        for card in self.selected_cards.iter_mut() {
+            card.seal = Some(seal.clone());
+        }
+    }
+
+    fn use_tarot(&mut self, tarot: &TarotCard) {
+        match tarot {
+            TarotCard::TheFool => {
+                if let Some(last) = self.last_consumable.clone() {
+                    let _ = self.add_consumable(last);
+                }
+            }
+            TarotCard::TheMagician => self.set_selected_enhancement(CardEnhancement::Lucky),
+            TarotCard::TheHighPriestess => {
+                for _ in 0..2 {
+                    let planet = self.roll_planet(RngStream::Consumable);
+                    let _ = self.add_consumable(Consumable::Planet(planet));
+                }
+            }
+            TarotCard::TheEmpress => self.set_selected_enhancement(CardEnhancement::Mult),
+            TarotCard::TheEmperor => {
+                for _ in 0..2 {
+                    let tarot = TarotCard::get_rand(self.rng.stream(RngStream::Consumable));
+                    let _ = self.add_consumable(Consumable::Tarot(tarot));
+                }
+            }
+            TarotCard::TheHierophant => self.set_selected_enhancement(CardEnhancement::Bonus),
+            TarotCard::TheLovers => self.set_selected_enhancement(CardEnhancement::Wild),
+            TarotCard::TheChariot => self.set_selected_enhancement(CardEnhancement::Steel),
+            TarotCard::Justice => self.set_selected_enhancement(CardEnhancement::Glass),
+            TarotCard::TheHermit => self.money += self.money.clamp(0, 20),
+            TarotCard::WheelOfFortune => {
+                if self.rng.stream(RngStream::Probability).gen_range(0..4) == 0 {
+                    let edition = CardEdition::get_rand(self.rng.stream(RngStream::Consumable));
+                    self.set_random_joker_edition(edition);
+                }
+            }
+            TarotCard::Strength => {
+                for card in self.selected_cards.iter_mut() {
+                    card.rank = card.rank.get_straight_next();
+                }
+            }
+            TarotCard::TheHangedMan => {
+                for card in std::mem::take(&mut self.selected_cards) {
+                    self.destroy_card(card);
+                }
+            }
+            // The right card is copied onto the left, by their place in the hand
+            TarotCard::Death => {
+                self.update_hand_order();
+                let position = |idx: usize| self.get_hand_position(self.selected_cards[idx].id);
+                let (left, right) = if position(0) < position(1) {
+                    (0, 1)
+                } else {
+                    (1, 0)
+                };
+
+                let right = self.selected_cards[right].clone();
+                let left = &mut self.selected_cards[left];
+                *left = Card {
+                    id: left.id,
+                    ..right
+                };
+            }
+            TarotCard::Temperance => {
+                let value: i32 = self.jokers.iter().map(|joker| joker.value as i32).sum();
+                self.money += value.min(50);
+            }
+            TarotCard::TheDevil => self.set_selected_enhancement(CardEnhancement::Gold),
+            TarotCard::TheTower => self.set_selected_enhancement(CardEnhancement::Stone),
+            TarotCard::TheStar => self.set_selected_suit(CardSuit::Diamonds),
+            TarotCard::TheMoon => self.set_selected_suit(CardSuit::Clubs),
+            TarotCard::TheSun => self.set_selected_suit(CardSuit::Hearts),
+            TarotCard::TheWorld => self.set_selected_suit(CardSuit::Spades),
+            TarotCard::Judgement => {
+                let joker = self.roll_joker(RngStream::Consumable);
+                let _ = self.add_joker(JokerCard::new(joker));
+            }
+        }
+    }
+
+    fn use_spectral(&mut self, spectral: &SpectralCard) {
+        match spectral {
+            SpectralCard::Familiar => {
+                self.destroy_random_in_hand(1);
+                let faces = FACE_CARDS.to_vec();
+                self.add_enhanced_cards_to_hand(&faces, 3);
+            }
+            SpectralCard::Grim => {
+                self.destroy_random_in_hand(1);
+                self.add_enhanced_cards_to_hand(&[CardRank::Ace], 2);
+            }
+            SpectralCard::Incantation => {
+                self.destroy_random_in_hand(1);
+                let numbers = CARD_RANKS
+                    .iter()
+                    .filter(|rank| !FACE_CARDS.contains(rank) && **rank != CardRank::Ace)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                self.add_enhanced_cards_to_hand(&numbers, 4);
+            }
+            SpectralCard::Talisman => self.set_selected_seal(CardSeal::Gold),
+            SpectralCard::Aura => {
+                let edition = CardEdition::get_rand(self.rng.stream(RngStream::Consumable));
+                for card in self.selected_cards.iter_mut() {
+                    card.edition = Some(edition.clone());
+                }
+            }
+            SpectralCard::Wraith => {
+                let rng = self.rng.stream(RngStream::Consumable);
+                if let Some(joker) = JokerType::get_rand(JokerRarity::Rare, rng) {
+                    let _ = self.add_joker(JokerCard::new(joker));
+                }
+                self.money = self.money.min(0);
+            }
+            SpectralCard::Sigil => {
+                let suit = CardSuit::get_rand(self.rng.stream(RngStream::Consumable));
+                for card in self.in_hand.iter_mut() {
+                    card.suit = suit.clone();
+                }
+            }
+            SpectralCard::Ouija => {
+                let rank = CardRank::get_rand(self.rng.stream(RngStream::Consumable));
+                for card in self.in_hand.iter_mut() {
+                    card.rank = rank.clone();
+                }
+                self.hand_size = self.hand_size.saturating_sub(1);
+            }
+            SpectralCard::Ectoplasm => {
+                self.set_random_joker_edition(CardEdition::Negative);
+                self.hand_size = self.hand_size.saturating_sub(1);
+            }
+            SpectralCard::Immolate => {
+                self.destroy_random_in_hand(5);
+                self.money += 20;
+            }
+            SpectralCard::Ankh => {
+                let rng = self.rng.stream(RngStream::Consumable);
+                if let Some(joker) = self.jokers.choose(rng).cloned() {
+                    self.jokers = vec![joker.clone(), joker];
+                }
+            }
+            SpectralCard::DejaVu => self.set_selected_seal(CardSeal::Red),
+            SpectralCard::Hex => {
+                if let Some(joker) = self.set_random_joker_edition(CardEdition::Polychrome) {
+                    self.jokers = vec![joker];
+                }
+            }
+            SpectralCard::Trance => self.set_selected_seal(CardSeal::Blue),
+            SpectralCard::Medium => self.set_selected_seal(CardSeal::Purple),
+            SpectralCard::Cryptid => {
+                let card = self.selected_cards[0].clone();
+                for _ in 0..2 {
+                    let copy = self.copy_card(&card);
+                    self.add_card_to_hand(copy);
+                }
+            }
+            SpectralCard::Soul => {
+                let legendaries = self.get_unowned_legendaries();
+                let rng = self.rng.stream(RngStream::Consumable);
+                if let Some(joker) = legendaries.choose(rng) {
+                    let _ = self.add_joker(JokerCard::new(joker.clone()));
+                }
+            }
+            SpectralCard::BlackHole => {
+                for planet in PLANET_CARDS.iter() {
+                    self.level_up_hand(planet.get_hand_type());
+                }
+            }
+        }
+    }
+
+    // The Soul never gives a Legendary joker the player already has
+    fn get_unowned_legendaries(&self) -> Vec<JokerType> {
+        JOKERS
+            .iter()
+            .filter(|joker| joker.get_rarity() == JokerRarity::Legendary && !self.has_joker(joker))
+            .cloned()
+            .collect()
+    }
+
+    // Gives a random joker without an edition the given edition, returning a copy of it
+    fn set_random_joker_edition(&mut self, edition: CardEdition) -> Option<JokerCard> {
+        let idx = self
+            .jokers
+            .iter()
+            .enumerate()
+            .filter(|(_, joker)| joker.edition == CardEdition::Base)
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>()
+            .choose(self.rng.stream(RngStream::Consumable))
+            .copied()?;
+
+        self.jokers[idx].edition = edition;
+        Some(self.jokers[idx].clone())
+    }
+
+    fn destroy_random_in_hand(&mut self, count: usize) {
+        let rng = self.rng.stream(RngStream::Consumable);
+        let ids = self
+            .in_hand
+            .choose_multiple(rng, count)
+            .map(|card| card.id)
+            .collect::<Vec<_>>();
+
+        let (destroyed, kept) = std::mem::take(&mut self.in_hand)
+            .into_iter()
+            .partition(|card| ids.contains(&card.id));
+
+        self.in_hand = kept;
+        for card in destroyed {
+            self.destroy_card(card);
+        }
+    }
+
+    fn add_enhanced_cards_to_hand(&mut self, ranks: &[CardRank], count: usize) {
+        for _ in 0..count {
+            let rng = self.rng.stream(RngStream::Consumable);
+            let rank = ranks.choose(rng).unwrap().clone();
+            let suit = CardSuit::get_rand(rng);
+            let enhancement = CardEnhancement::get_rand(rng);
+
+            let mut card = self.create_card(rank, suit);
+            card.enhancement = Some(enhancement);
+            self.add_card_to_hand(card);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+    use crate::{card::CardId, new_test_game, stake::GameStake, GameStartingDeck};
+
+    // Starts a game on its first blind, holding no consumables
+    fn new_game() -> GameState {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 0);
+        state.select_blind().unwrap();
+        state.consumables.clear();
+        state
+    }
+
+    // Uses the consumable on the first `targets` cards in hand, returning their ids
+    fn use_on(state: &mut GameState, consumable: Consumable, targets: usize) -> Vec<CardId> {
+        let ids = state.in_hand[..targets]
+            .iter()
+            .map(|card| card.id)
+            .collect::<Vec<_>>();
+        for id in ids.iter() {
+            state.select_card(*id).unwrap();
+        }
+
+        state.consumables = vec![ConsumableCard::new(consumable)];
+        state.use_consumable(0).unwrap();
+        ids
+    }
+
+    fn get_card(state: &GameState, id: CardId) -> &Card {
+        state.in_hand.iter().find(|card| card.id == id).unwrap()
+    }
+
+    #[test]
+    fn test_enhancement_tarots() {
+        for (tarot, enhancement) in [
+            (TarotCard::TheMagician, CardEnhancement::Lucky),
+            (TarotCard::TheEmpress, CardEnhancement::Mult),
+            (TarotCard::TheHierophant, CardEnhancement::Bonus),
+            (TarotCard::TheLovers, CardEnhancement::Wild),
+            (TarotCard::TheChariot, CardEnhancement::Steel),
+            (TarotCard::Justice, CardEnhancement::Glass),
+            (TarotCard::TheDevil, CardEnhancement::Gold),
+            (TarotCard::TheTower, CardEnhancement::Stone),
+        ] {
+            let mut state = new_game();
+            let targets = tarot.get_max_targets();
+            for id in use_on(&mut state, Consumable::Tarot(tarot.clone()), targets) {
+                assert_eq!(get_card(&state, id).enhancement, Some(enhancement.clone()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_suit_tarots() {
+        for (tarot, suit) in [
+            (TarotCard::TheStar, CardSuit::Diamonds),
+            (TarotCard::TheMoon, CardSuit::Clubs),
+            (TarotCard::TheSun, CardSuit::Hearts),
+            (TarotCard::TheWorld, CardSuit::Spades),
+        ] {
+            let mut state = new_game();
+            for id in use_on(&mut state, Consumable::Tarot(tarot), 3) {
+                assert_eq!(get_card(&state, id).suit, suit);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rank_tarots() {
+        let mut state = new_game();
+        let before = state.in_hand[0].clone();
+        let ids = use_on(&mut state, Consumable::Tarot(TarotCard::Strength), 1);
+        assert_eq!(
+            get_card(&state, ids[0]).rank,
+            before.rank.get_straight_next()
+        );
+
+        // Death turns the left card into a copy of the right one
+        let right = state.in_hand[1].clone();
+        let ids = use_on(&mut state, Consumable::Tarot(TarotCard::Death), 2);
+        let left = get_card(&state, ids[0]);
+        assert_eq!((&left.rank, &left.suit), (&right.rank, &right.suit));
+
+        // Selecting right to left still copies onto the left card, and both
+        // go back to their places afterwards
+        let left = state.in_hand[2].clone();
+        let right = state.in_hand[3].clone();
+        assert_ne!((&left.rank, &left.suit), (&right.rank, &right.suit));
+        state.select_card(right.id).unwrap();
+        state.select_card(left.id).unwrap();
+        state.consumables = vec![ConsumableCard::new(Consumable::Tarot(TarotCard::Death))];
+        state.use_consumable(0).unwrap();
+        let copied = get_card(&state, left.id);
+        assert_eq!((&copied.rank, &copied.suit), (&right.rank, &right.suit));
+        assert_eq!(state.in_hand[2].id, left.id);
+        assert_eq!(state.in_hand[3].id, right.id);
+
+        let hand = state.in_hand.len();
+        let ids = use_on(&mut state, Consumable::Tarot(TarotCard::TheHangedMan), 2);
+        assert_eq!(state.in_hand.len(), hand - 2);
+        assert!(state
+            .deck
+            .destroyed()
+            .iter()
+            .all(|card| ids.contains(&card.id)));
+    }
+
+    #[test]
+    fn test_money_tarots() {
+        let mut state = new_game();
+        state.money = 15;
+        use_on(&mut state, Consumable::Tarot(TarotCard::TheHermit), 0);
+        assert_eq!(state.money, 30);
+        use_on(&mut state, Consumable::Tarot(TarotCard::TheHermit), 0);
+        assert_eq!(state.money, 50);
+
+        state.jokers = vec![JokerCard::new(JokerType::Joker); 3];
+        use_on(&mut state, Consumable::Tarot(TarotCard::Temperance), 0);
+        assert_eq!(state.money, 53);
+    }
+
+    #[test]
+    fn test_tarots_that_make_cards() {
+        let mut state = new_game();
+        use_on(
+            &mut state,
+            Consumable::Tarot(TarotCard::TheHighPriestess),
+            0,
+        );
+        assert_eq!(state.consumables.len(), 2);
+        assert!(state
+            .consumables
+            .iter()
+            .all(|card| matches!(card.consumable, Consumable::Planet(_))));
+
+        state.consumables.clear();
+        use_on(&mut state, Consumable::Tarot(TarotCard::TheEmperor), 0);
+        assert_eq!(state.consumables.len(), 2);
+
+        use_on(&mut state, Consumable::Tarot(TarotCard::Judgement), 0);
+        assert_eq!(state.jokers.len(), 1);
+
+        // The Fool copies the last tarot or planet, and can't be used before one
+        let mut state = new_game();
+        state.consumables = vec![ConsumableCard::new(Consumable::Tarot(TarotCard::TheFool))];
+        assert_eq!(state.use_consumable(0), Err(GameError::CannotUseConsumable));
+        use_on(&mut state, Consumable::Planet(PlanetCard::Mars), 0);
+        use_on(&mut state, Consumable::Tarot(TarotCard::TheFool), 0);
+        assert_eq!(
+            state.consumables[0].consumable,
+            Consumable::Planet(PlanetCard::Mars)
+        );
+    }
+
+    #[test]
+    fn test_tarot_targets() {
+        let mut state = new_game();
+        for idx in 0..3 {
+            state.select_card(state.in_hand[idx].id).unwrap();
+        }
+
+        // The Magician takes at most two cards
+        state.consumables = vec![ConsumableCard::new(Consumable::Tarot(
+            TarotCard::TheMagician,
+        ))];
+        assert_eq!(state.use_consumable(0), Err(GameError::CannotUseConsumable));
+        assert_eq!(state.consumables.len(), 1);
+    }
+
+    #[test]
+    fn test_planets_level_up() {
+        let mut state = new_game();
+        use_on(&mut state, Consumable::Planet(PlanetCard::Jupiter), 0);
+        assert_eq!(state.get_hand_level(&HandType::Flush), 2);
+
+        use_on(&mut state, Consumable::Spectral(SpectralCard::BlackHole), 0);
+        assert_eq!(state.get_hand_level(&HandType::Flush), 3);
+        assert_eq!(state.get_hand_level(&HandType::HighCard), 2);
+    }
+
+    #[test]
+    fn test_seal_and_edition_spectrals() {
+        for (spectral, seal) in [
+            (SpectralCard::Talisman, CardSeal::Gold),
+            (SpectralCard::DejaVu, CardSeal::Red),
+            (SpectralCard::Trance, CardSeal::Blue),
+            (SpectralCard::Medium, CardSeal::Purple),
+        ] {
+            let mut state = new_game();
+            let ids = use_on(&mut state, Consumable::Spectral(spectral), 1);
+            assert_eq!(get_card(&state, ids[0]).seal, Some(seal));
+        }
+
+        let mut state = new_game();
+        let ids = use_on(&mut state, Consumable::Spectral(SpectralCard::Aura), 1);
+        assert!(get_card(&state, ids[0]).edition.is_some());
+    }
+
+    #[test]
+    fn test_spectrals_that_change_the_hand() {
+        let mut state = new_game();
+        use_on(&mut state, Consumable::Spectral(SpectralCard::Sigil), 0);
+        assert!(state.in_hand.iter().map(|card| &card.suit).all_equal());
+
+        let hand_size = state.hand_size;
+        use_on(&mut state, Consumable::Spectral(SpectralCard::Ouija), 0);
+        assert!(state.in_hand.iter().map(|card| &card.rank).all_equal());
+        assert_eq!(state.hand_size, hand_size - 1);
+
+        // Cryptid adds two copies of the card to the hand
+        let mut state = new_game();
+        let hand = state.in_hand.len();
+        let ids = use_on(&mut state, Consumable::Spectral(SpectralCard::Cryptid), 1);
+        let target = get_card(&state, ids[0]);
+        let copies = state
+            .in_hand
+            .iter()
+            .filter(|card| card.id != target.id)
+            .filter(|card| card.rank == target.rank && card.suit == target.suit)
+            .count();
+        assert!(copies >= 2);
+        assert_eq!(state.in_hand.len(), hand + 2);
+    }
+
+    #[test]
+    fn test_spectrals_that_destroy_cards() {
+        for (spectral, added) in [
+            (SpectralCard::Familiar, 3),
+            (SpectralCard::Grim, 2),
+            (SpectralCard::Incantation, 4),
+        ] {
+            let mut state = new_game();
+            let hand = state.in_hand.len();
+            use_on(&mut state, Consumable::Spectral(spectral), 0);
+            assert_eq!(state.in_hand.len(), hand - 1 + added);
+            assert_eq!(state.deck.destroyed().len(), 1);
+        }
+
+        let mut state = new_game();
+        state.money = 4;
+        let hand = state.in_hand.len();
+        use_on(&mut state, Consumable::Spectral(SpectralCard::Immolate), 0);
+        assert_eq!(state.in_hand.len(), hand - 5);
+        assert_eq!(state.money, 24);
+    }
+
+    #[test]
+    fn test_spectrals_that_change_jokers() {
+        let mut state = new_game();
+        state.money = 10;
+        use_on(&mut state, Consumable::Spectral(SpectralCard::Wraith), 0);
+        assert_eq!(state.jokers[0].joker.get_rarity(), JokerRarity::Rare);
+        assert_eq!(state.money, 0);
+
+        use_on(&mut state, Consumable::Spectral(SpectralCard::Ankh), 0);
+        assert_eq!(state.jokers.len(), 2);
+        assert_eq!(state.jokers[0].joker, state.jokers[1].joker);
+
+        use_on(&mut state, Consumable::Spectral(SpectralCard::Hex), 0);
+        assert_eq!(state.jokers.len(), 1);
+        assert_eq!(state.jokers[0].edition, CardEdition::Polychrome);
+
+        let mut state = new_game();
+        state.jokers = vec![JokerCard::new(JokerType::Joker)];
+        let hand_size = state.hand_size;
+        use_on(&mut state, Consumable::Spectral(SpectralCard::Ectoplasm), 0);
+        assert_eq!(state.jokers[0].edition, CardEdition::Negative);
+        assert_eq!(state.hand_size, hand_size - 1);
+    }
+
+    #[test]
+    fn test_soul_makes_a_legendary_joker() {
+        let mut state = new_game();
+        use_on(&mut state, Consumable::Spectral(SpectralCard::Soul), 0);
+
+        assert_eq!(state.jokers.len(), 1);
+        assert_eq!(state.jokers[0].joker.get_rarity(), JokerRarity::Legendary);
+
+        // With every other Legendary owned, only the missing one can come up
+        state.joker_slots = 10;
+        state.jokers = JOKERS
+            .iter()
+            .filter(|joker| joker.get_rarity() == JokerRarity::Legendary)
+            .map(|joker| JokerCard::new(joker.clone()))
+            .collect();
+        let soul = Consumable::Spectral(SpectralCard::Soul);
+        assert!(!state.can_use_consumable(&soul));
+
+        let missing = state.jokers.remove(2).joker;
+        use_on(&mut state, soul, 0);
+        assert_eq!(state.jokers.last().unwrap().joker, missing);
+    }
+}
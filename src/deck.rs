@@ -0,0 +1,193 @@
+use std::collections::HashSet;
+
+use rand::{seq::SliceRandom, Rng};
+
+use crate::card::{Card, CardId, CardIdAllocator, CardRank, CardSuit};
+
+/// Every playing card the player owns this run, and which pile it is in.
+///
+/// Cards in hand are held by `GameState`; the deck tracks the rest and checks
+/// that, together, each card in the full deck is in exactly one place.
+#[derive(Clone, Debug)]
+pub struct Deck {
+    ids: CardIdAllocator,
+
+    // IDs of every card owned this run, in the order they were added
+    full_deck: Vec<CardId>,
+    // Shuffled; the top of the pile is the end of the vec
+    draw_pile: Vec<Card>,
+    discard_pile: Vec<Card>,
+    destroyed: Vec<Card>,
+}
+
+impl Deck {
+    pub fn new(cards: Vec<Card>, ids: CardIdAllocator) -> Self {
+        Self {
+            ids,
+            full_deck: cards.iter().map(|card| card.id).collect(),
+            draw_pile: cards,
+            discard_pile: vec![],
+            destroyed: vec![],
+        }
+    }
+
+    /// Creates a card with a fresh ID. It isn't part of the deck until it is
+    /// passed to `add_card` or `register`.
+    pub fn new_card(&mut self, rank: CardRank, suit: CardSuit) -> Card {
+        self.ids.new_card(rank, suit)
+    }
+
+    pub fn copy_card(&mut self, card: &Card) -> Card {
+        self.ids.copy_card(card)
+    }
+
+    // Adds a card that goes somewhere other than the draw pile, e.g. straight into the hand
+    pub fn register(&mut self, card: &Card) {
+        self.full_deck.push(card.id);
+    }
+
+    pub fn add_card(&mut self, card: Card) {
+        self.register(&card);
+        self.draw_pile.push(card);
+    }
+
+    pub fn full_deck(&self) -> &[CardId] {
+        &self.full_deck
+    }
+
+    pub fn draw_pile(&self) -> &[Card] {
+        &self.draw_pile
+    }
+
+    pub fn discard_pile(&self) -> &[Card] {
+        &self.discard_pile
+    }
+
+    pub fn destroyed(&self) -> &[Card] {
+        &self.destroyed
+    }
+
+    pub fn draw(&mut self) -> Option<Card> {
+        self.draw_pile.pop()
+    }
+
+    // The next `count` cards to be drawn, next card first
+    pub fn peek(&self, count: usize) -> impl Iterator<Item = &Card> {
+        self.draw_pile.iter().rev().take(count)
+    }
+
+    pub fn discard(&mut self, card: Card) {
+        self.discard_pile.push(card);
+    }
+
+    pub fn destroy(&mut self, card: Card) {
+        self.full_deck.retain(|id| *id != card.id);
+        self.destroyed.push(card);
+    }
+
+    pub fn shuffle(&mut self, rng: &mut impl Rng) {
+        self.draw_pile.shuffle(rng);
+    }
+
+    /// Puts the discard pile and the given cards (whatever was left in hand)
+    /// back into the draw pile and shuffles it, as happens at the end of a round.
+    /// Every card comes back face up and no longer debuffed.
+    pub fn reshuffle(&mut self, cards: impl IntoIterator<Item = Card>, rng: &mut impl Rng) {
+        self.draw_pile.append(&mut self.discard_pile);
+        self.draw_pile.extend(cards);
+        for card in self.draw_pile.iter_mut() {
+            card.clear_round_status();
+        }
+        self.shuffle(rng);
+    }
+
+    /// Checks that every card in the full deck is in exactly one place: the
+    /// draw pile, the discard pile or `held`. Returns the first problem found.
+    pub fn verify<'a>(&'a self, held: impl IntoIterator<Item = &'a Card>) -> Result<(), String> {
+        let owned = self.full_deck.iter().copied().collect::<HashSet<_>>();
+        if owned.len() != self.full_deck.len() {
+            return Err("full deck lists a card twice".to_string());
+        }
+
+        let mut seen = HashSet::new();
+        let cards = self
+            .draw_pile
+            .iter()
+            .chain(self.discard_pile.iter())
+            .chain(held);
+
+        for card in cards {
+            if !owned.contains(&card.id) {
+                return Err(format!("card {} is not in the full deck", card.id));
+            }
+
+            if !seen.insert(card.id) {
+                return Err(format!("card {} is in more than one place", card.id));
+            }
+        }
+
+        if let Some(id) = owned.iter().find(|id| !seen.contains(id)) {
+            return Err(format!("card {} has been lost", id));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::card::{CARD_RANKS, CARD_SUITS};
+
+    fn new_deck() -> Deck {
+        let mut ids = CardIdAllocator::new();
+        let cards = CARD_SUITS
+            .iter()
+            .flat_map(|suit| CARD_RANKS.iter().map(move |rank| (rank, suit)))
+            .map(|(rank, suit)| ids.new_card(rank.clone(), suit.clone()))
+            .collect();
+
+        Deck::new(cards, ids)
+    }
+
+    #[test]
+    fn test_draw_shrinks_draw_pile() {
+        let mut deck = new_deck();
+        let next = deck.peek(1).next().unwrap().id;
+
+        let card = deck.draw().unwrap();
+        assert_eq!(card.id, next);
+        assert_eq!(deck.draw_pile().len(), 51);
+        assert_eq!(deck.verify([&card]), Ok(()));
+    }
+
+    #[test]
+    fn test_reshuffle_returns_everything() {
+        let mut deck = new_deck();
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let hand = (0..8).map(|_| deck.draw().unwrap()).collect::<Vec<_>>();
+        let discarded = deck.draw().unwrap();
+        deck.discard(discarded);
+
+        deck.reshuffle(hand, &mut rng);
+        assert_eq!(deck.draw_pile().len(), 52);
+        assert!(deck.discard_pile().is_empty());
+        assert_eq!(deck.verify([]), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_catches_lost_and_duplicate_cards() {
+        let mut deck = new_deck();
+
+        let card = deck.draw().unwrap();
+        assert!(deck.verify([]).is_err());
+        assert!(deck.verify([&card, &card]).is_err());
+
+        deck.destroy(card);
+        assert_eq!(deck.full_deck().len(), 51);
+        assert_eq!(deck.verify([]), Ok(()));
+    }
+}
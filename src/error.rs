@@ -0,0 +1,70 @@
+use std::fmt;
+
+use crate::{card::CardId, GamePhase, PlayerMoney};
+
+/// An action the game refused. The state is left untouched, so the caller can
+/// pick a different move and carry on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GameError {
+    CardNotInHand(CardId),
+    CardNotSelected(CardId),
+    DuplicateCard(CardId),
+    CardFaceDown(CardId),
+    NoCardsSelected,
+    TooManyCardsSelected(usize),
+    NoHandsLeft,
+    NoDiscardsLeft,
+    InvalidIndex(usize),
+    NotEnoughMoney {
+        cost: PlayerMoney,
+        money: PlayerMoney,
+    },
+    JokerSlotsFull,
+    ConsumableSlotsFull,
+    CannotUseConsumable,
+    PackOpen,
+    PackNotOpen,
+    CannotSkipBlind,
+    GameOver,
+    WrongPhase {
+        expected: GamePhase,
+        actual: GamePhase,
+    },
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameError::CardNotInHand(id) => write!(f, "card {} is not in hand", id),
+            GameError::CardNotSelected(id) => write!(f, "card {} is not selected", id),
+            GameError::DuplicateCard(id) => write!(f, "card {} is listed more than once", id),
+            GameError::CardFaceDown(id) => write!(f, "card {} is face down", id),
+            GameError::NoCardsSelected => write!(f, "no cards selected"),
+            GameError::TooManyCardsSelected(limit) => {
+                write!(f, "at most {} cards can be selected", limit)
+            }
+            GameError::NoHandsLeft => write!(f, "no hands left"),
+            GameError::NoDiscardsLeft => write!(f, "no discards left"),
+            GameError::InvalidIndex(idx) => write!(f, "nothing at index {}", idx),
+            GameError::NotEnoughMoney { cost, money } => {
+                write!(f, "costs ${}, but only ${} available", cost, money)
+            }
+            GameError::JokerSlotsFull => write!(f, "no free joker slots"),
+            GameError::ConsumableSlotsFull => write!(f, "no free consumable slots"),
+            GameError::CannotUseConsumable => write!(f, "consumable can't be used right now"),
+            GameError::PackOpen => write!(f, "a booster pack is open"),
+            GameError::PackNotOpen => write!(f, "no booster pack is open"),
+            GameError::CannotSkipBlind => write!(f, "the boss blind can't be skipped"),
+            GameError::GameOver => write!(f, "the run is over"),
+            GameError::WrongPhase { expected, actual } => {
+                write!(
+                    f,
+                    "expected phase {:?}, but game is in {:?}",
+                    expected, actual
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for GameError {}
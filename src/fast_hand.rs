@@ -0,0 +1,351 @@
+use crate::{
+    card::{Card, CardEnhancement, CARD_SUITS},
+    hand::{HandRules, HandType},
+};
+
+// A second hand evaluator for simulations and `HandType::get_possible_hands`.
+// It gives the same answers as `HandType::from_cards`, but works on bitmasks
+// and a straight lookup table so classifying a hand never touches the heap.
+
+// Most cards one call can classify, one bit each in the scoring mask
+pub const MAX_PACKED_CARDS: usize = 16;
+
+const RANK_COUNT: usize = 13;
+const RANK_MASKS: usize = 1 << RANK_COUNT;
+
+// Ranks Two through Nine, none of which can be in a Royal Flush
+const BELOW_TEN: u16 = 0xFF;
+
+/// A card reduced to what classifying a hand needs. Suits are packed under
+/// the rules in play, so a Wild card or a Smeared suit sets several bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PackedCard {
+    // 0 for Two up to 12 for Ace. Stone cards have no rank or suit
+    rank: u8,
+    // Bit i is set if the card counts as `CARD_SUITS[i]`
+    suits: u8,
+    stone: bool,
+}
+
+impl PackedCard {
+    pub fn new(card: &Card, rules: &HandRules) -> Self {
+        let mut suits = 0;
+        for (idx, suit) in CARD_SUITS.iter().enumerate() {
+            if rules.is_suit(card, suit) {
+                suits |= 1 << idx;
+            }
+        }
+
+        Self {
+            rank: card.rank.clone() as u8,
+            suits,
+            stone: card.enhancement == Some(CardEnhancement::Stone),
+        }
+    }
+}
+
+/// Classifies packed cards the same way as `HandType::from_cards`. Returns
+/// the hand type and a mask of the scoring cards, where bit i is `cards[i]`,
+/// or `None` if no cards were given or there are more than fit in the mask.
+pub fn classify(cards: &[PackedCard], rules: &HandRules) -> Option<(HandType, u16)> {
+    if cards.is_empty() || cards.len() > MAX_PACKED_CARDS {
+        return None;
+    }
+
+    let hand_length = if rules.four_card_hands { 4 } else { 5 };
+
+    let mut rank_counts = [0u8; RANK_COUNT];
+    let mut suit_counts = [0u8; CARD_SUITS.len()];
+    let mut rank_mask = 0u16;
+    for card in cards.iter().filter(|card| !card.stone) {
+        rank_counts[card.rank as usize] += 1;
+        rank_mask |= 1 << card.rank;
+        for (idx, count) in suit_counts.iter_mut().enumerate() {
+            if card.suits & (1 << idx) != 0 {
+                *count += 1;
+            }
+        }
+    }
+
+    // Cards matching a test, as a mask over `cards`
+    let select = |test: &dyn Fn(&PackedCard) -> bool| {
+        cards
+            .iter()
+            .enumerate()
+            .filter(|(_, card)| test(card))
+            .fold(0u16, |mask, (idx, _)| mask | (1 << idx))
+    };
+    let of_rank = |rank: usize| select(&|card| !card.stone && card.rank as usize == rank);
+
+    // The nth highest rank with exactly this many cards
+    let same = |count: u8, nth: usize| {
+        (0..RANK_COUNT)
+            .rev()
+            .filter(|rank| rank_counts[*rank] == count)
+            .nth(nth)
+    };
+
+    let five = same(5, 0);
+    let four = same(4, 0);
+    let three = same(3, 0);
+    let pair = same(2, 0);
+    let second_pair = same(2, 1);
+
+    let flush = suit_counts
+        .iter()
+        .position(|count| *count as usize >= hand_length)
+        .map(|suit| select(&|card| card.suits & (1 << suit) != 0));
+
+    let straight_ranks = STRAIGHTS[get_straight_table(rules)][rank_mask as usize];
+    let straight = (straight_ranks != 0)
+        .then(|| select(&|card| !card.stone && straight_ranks & (1 << card.rank) != 0));
+
+    let (hand_type, parts) = if let (Some(five), Some(_)) = (five, flush) {
+        (HandType::FlushFive, of_rank(five))
+    } else if let (Some(three), Some(pair), Some(_)) = (three, pair, flush) {
+        (HandType::FlushHouse, of_rank(three) | of_rank(pair))
+    } else if let Some(five) = five {
+        (HandType::FiveOfAKind, of_rank(five))
+    } else if let (Some(straight), Some(flush)) = (straight, flush) {
+        let hand_type = if straight_ranks & BELOW_TEN == 0 {
+            HandType::RoyalFlush
+        } else {
+            HandType::StraightFlush
+        };
+        (hand_type, straight | flush)
+    } else if let Some(four) = four {
+        (HandType::FourOfAKind, of_rank(four))
+    } else if let (Some(three), Some(pair)) = (three, pair) {
+        (HandType::FullHouse, of_rank(three) | of_rank(pair))
+    } else if let Some(flush) = flush {
+        (HandType::Flush, flush)
+    } else if let Some(straight) = straight {
+        (HandType::Straight, straight)
+    } else if let Some(three) = three {
+        (HandType::ThreeOfAKind, of_rank(three))
+    } else if let (Some(pair), Some(second_pair)) = (pair, second_pair) {
+        (HandType::TwoPair, of_rank(pair) | of_rank(second_pair))
+    } else if let Some(pair) = pair {
+        (HandType::Pair, of_rank(pair))
+    } else {
+        // First of the highest ranked cards, if any card has a rank
+        let high_rank = 15 - rank_mask.leading_zeros() as i32;
+        let first = cards
+            .iter()
+            .position(|card| !card.stone && card.rank as i32 == high_rank);
+        (HandType::HighCard, first.map_or(0, |first| 1 << first))
+    };
+
+    let all_cards = select(&|_| true);
+    let scoring = if rules.all_cards_score {
+        all_cards
+    } else {
+        parts | select(&|card| card.stone)
+    };
+
+    Some((hand_type, scoring))
+}
+
+fn get_straight_table(rules: &HandRules) -> usize {
+    (rules.four_card_hands as usize) << 1 | rules.straight_gaps as usize
+}
+
+// For every set of ranks, the ranks in its straight or 0 if there is none.
+// Indexed by `get_straight_table`, then by rank mask
+static STRAIGHTS: [[u16; RANK_MASKS]; 4] = [
+    build_straights(5, false),
+    build_straights(5, true),
+    build_straights(4, false),
+    build_straights(4, true),
+];
+
+// Same walk as `hand::get_straight`, run once per rank mask at compile time
+const fn build_straights(hand_length: usize, gaps: bool) -> [u16; RANK_MASKS] {
+    let mut table = [0u16; RANK_MASKS];
+
+    let mut rank_mask = 0;
+    while rank_mask < RANK_MASKS {
+        // Bit 0 is a low Ace, bits 1..=13 are Two through Ace
+        let has_rank = (rank_mask << 1) | (rank_mask >> (RANK_COUNT - 1));
+
+        let mut run = 0;
+        let mut run_ranks = 0usize;
+        let mut skipped = false;
+        let mut found = false;
+
+        let mut idx = 0;
+        while idx <= RANK_COUNT {
+            if has_rank & (1 << idx) != 0 {
+                run += 1;
+                run_ranks |= 1 << idx;
+                skipped = false;
+            } else if gaps && !skipped && idx != RANK_COUNT {
+                skipped = true;
+            } else if found {
+                break;
+            } else {
+                run = 0;
+                run_ranks = 0;
+                skipped = false;
+            }
+
+            if run >= hand_length {
+                found = true;
+            }
+
+            idx += 1;
+        }
+
+        if found {
+            // Back to card ranks, with a low Ace counting as the Ace
+            let ranks = (run_ranks >> 1) | ((run_ranks & 1) << (RANK_COUNT - 1));
+            table[rank_mask] = ranks as u16;
+        }
+
+        rank_mask += 1;
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+    use crate::card::{CardIdAllocator, CardRank, CardSuit, CARD_RANKS};
+
+    fn reference(cards: &[&Card], rules: &HandRules) -> (HandType, u16) {
+        let (hand_type, played) = HandType::from_cards(cards.to_vec(), rules).unwrap();
+        let scoring = played
+            .iter()
+            .enumerate()
+            .filter(|(_, played)| played.is_scoring)
+            .fold(0, |mask, (idx, _)| mask | (1 << idx));
+        (hand_type, scoring)
+    }
+
+    fn fast(cards: &[&Card], rules: &HandRules) -> (HandType, u16) {
+        let packed = cards
+            .iter()
+            .map(|card| PackedCard::new(card, rules))
+            .collect::<Vec<_>>();
+        classify(&packed, rules).unwrap()
+    }
+
+    // Every combination of the switches in `HandRules`
+    fn all_rules() -> Vec<HandRules> {
+        (0..32)
+            .map(|bits: u8| HandRules {
+                four_card_hands: bits & 1 != 0,
+                straight_gaps: bits & 2 != 0,
+                merged_suits: bits & 4 != 0,
+                wild_suits: bits & 8 != 0,
+                all_cards_score: bits & 16 != 0,
+            })
+            .collect()
+    }
+
+    fn check(cards: &[Card], rules: &HandRules) {
+        let cards = cards.iter().collect::<Vec<_>>();
+        assert_eq!(
+            fast(&cards, rules),
+            reference(&cards, rules),
+            "{:?} {:?}",
+            cards,
+            rules
+        );
+    }
+
+    // Every multiset of up to 5 ranks, played in both orders, with and without
+    // a flush, under every rule that looks at ranks
+    #[test]
+    fn test_classify_matches_every_rank_set() {
+        let rules = all_rules()
+            .into_iter()
+            .filter(|rules| !rules.merged_suits && !rules.wild_suits)
+            .collect::<Vec<_>>();
+
+        let mut ids = CardIdAllocator::new();
+        for size in 1..=5 {
+            for ranks in CARD_RANKS.iter().combinations_with_replacement(size) {
+                // Alternating suits never make a flush, even with Four Fingers
+                let mixed = ranks
+                    .iter()
+                    .zip(CARD_SUITS.iter().cycle())
+                    .map(|(rank, suit)| ids.new_card((*rank).clone(), suit.clone()))
+                    .collect::<Vec<_>>();
+                let suited = ranks
+                    .iter()
+                    .map(|rank| ids.new_card((*rank).clone(), CardSuit::Spades))
+                    .collect::<Vec<_>>();
+
+                for mut cards in [mixed, suited] {
+                    for rules in rules.iter() {
+                        check(&cards, rules);
+                        cards.reverse();
+                        check(&cards, rules);
+                    }
+                }
+            }
+        }
+    }
+
+    // Every mix of suits, Wild cards and Stone cards over up to 5 cards,
+    // under every rule that looks at suits
+    #[test]
+    fn test_classify_matches_every_suit_mix() {
+        use CardRank::*;
+
+        let rules = all_rules()
+            .into_iter()
+            .filter(|rules| !rules.all_cards_score)
+            .collect::<Vec<_>>();
+
+        // Each card is one of the four suits, a Wild card or a Stone card
+        let kinds = CARD_SUITS.len() + 2;
+
+        let mut ids = CardIdAllocator::new();
+        for ranks in [
+            [Two, Five, Eight, Jack, King],
+            [Ten, Jack, Queen, King, Ace],
+        ] {
+            for size in 1..=5 {
+                for kind_idx in 0..kinds.pow(size as u32) {
+                    let cards = (0..size)
+                        .map(|idx| {
+                            let kind = kind_idx / kinds.pow(idx as u32) % kinds;
+                            let suit = CARD_SUITS[kind % CARD_SUITS.len()].clone();
+                            let mut card = ids.new_card(ranks[idx].clone(), suit);
+                            card.enhancement = match kind {
+                                4 => Some(CardEnhancement::Wild),
+                                5 => Some(CardEnhancement::Stone),
+                                _ => None,
+                            };
+                            card
+                        })
+                        .collect::<Vec<_>>();
+
+                    for rules in rules.iter() {
+                        check(&cards, rules);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_no_cards() {
+        assert_eq!(classify(&[], &HandRules::default()), None);
+    }
+
+    #[test]
+    fn test_too_many_cards() {
+        let rules = HandRules::default();
+        let mut ids = CardIdAllocator::new();
+        let card = PackedCard::new(&ids.new_card(CardRank::Ace, CardSuit::Spades), &rules);
+        let cards = vec![card; MAX_PACKED_CARDS + 1];
+        assert_eq!(classify(&cards, &rules), None);
+        assert!(classify(&cards[1..], &rules).is_some());
+    }
+}
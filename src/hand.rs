@@ -1,14 +1,86 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use itertools::Itertools;
 
-use crate::card::{Card, CardRank, CardSuit};
+use crate::{
+    card::{Card, CardEnhancement, CardRank, CardSuit, CARD_SUITS},
+    error::GameError,
+    fast_hand::{classify, PackedCard},
+    scoring::ChipsAndMult,
+    GameState,
+};
+
+/// The run's record of one hand type. Royal Flush shares the Straight
+/// Flush record, as it does in the game.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HandStats {
+    pub level: u8,
+    pub played: u32,
+    pub played_this_round: u32,
+}
 
-pub type HandLevels = HashMap<HandType, u8>;
+impl Default for HandStats {
+    fn default() -> Self {
+        Self {
+            level: 1,
+            played: 0,
+            played_this_round: 0,
+        }
+    }
+}
 
-pub struct ChipsAndMult {
-    pub mult: u32,
-    pub chips: u32,
+// Most cards that can be played as one hand
+pub const MAX_PLAYED_CARDS: usize = 5;
+
+/// Switches that change how played cards are classified. Jokers and
+/// enhancements turn these on while they are active; see
+/// `GameState::get_hand_rules`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HandRules {
+    // Four Fingers: flushes and straights need only 4 cards
+    pub four_card_hands: bool,
+    // Shortcut: straights may skip one rank between cards, e.g. 2-4-6-7-8
+    pub straight_gaps: bool,
+    // Smeared Joker: hearts and diamonds are one suit, and spades and clubs another
+    pub merged_suits: bool,
+    // Wild cards count as every suit
+    pub wild_suits: bool,
+    // Splash: every played card scores, not just the ones making the hand
+    pub all_cards_score: bool,
+}
+
+/// A played card and whether it is scored along with the hand. Kickers are
+/// played but not scored, e.g. the three odd cards next to a Pair.
+#[derive(Clone, Copy, Debug)]
+pub struct PlayedCard<'a> {
+    pub card: &'a Card,
+    pub is_scoring: bool,
+}
+
+impl HandRules {
+    pub fn is_suit(&self, card: &Card, suit: &CardSuit) -> bool {
+        if !card.has_rank() {
+            return false;
+        }
+
+        if self.wild_suits && card.enhancement == Some(CardEnhancement::Wild) {
+            return true;
+        }
+
+        if self.merged_suits {
+            return card.suit.is_red() == suit.is_red();
+        }
+
+        card.suit == *suit
+    }
+
+    fn get_hand_length(&self) -> usize {
+        if self.four_card_hands {
+            4
+        } else {
+            5
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -30,309 +102,908 @@ pub enum HandType {
     FlushFive,
 }
 
+/// Chips and mult for a hand at level 1, and how much each level above that adds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HandValue {
+    pub chips: u32,
+    pub mult: u32,
+    pub level_chips: u32,
+    pub level_mult: u32,
+}
+
+pub const HAND_TYPES: [HandType; 13] = [
+    HandType::HighCard,
+    HandType::Pair,
+    HandType::TwoPair,
+    HandType::ThreeOfAKind,
+    HandType::Straight,
+    HandType::Flush,
+    HandType::FullHouse,
+    HandType::FourOfAKind,
+    HandType::StraightFlush,
+    HandType::RoyalFlush,
+    HandType::FiveOfAKind,
+    HandType::FlushHouse,
+    HandType::FlushFive,
+];
+
+// https://balatrogame.fandom.com/wiki/Poker_Hands
+// A Royal Flush is scored and levelled as a Straight Flush
+const HAND_VALUES: [(HandType, HandValue); 12] = [
+    (HandType::HighCard, hand_value(5, 1, 10, 1)),
+    (HandType::Pair, hand_value(10, 2, 15, 1)),
+    (HandType::TwoPair, hand_value(20, 2, 20, 1)),
+    (HandType::ThreeOfAKind, hand_value(30, 3, 20, 2)),
+    (HandType::Straight, hand_value(30, 4, 30, 3)),
+    (HandType::Flush, hand_value(35, 4, 15, 2)),
+    (HandType::FullHouse, hand_value(40, 4, 25, 2)),
+    (HandType::FourOfAKind, hand_value(60, 7, 30, 3)),
+    (HandType::StraightFlush, hand_value(100, 8, 40, 4)),
+    (HandType::FiveOfAKind, hand_value(120, 12, 35, 3)),
+    (HandType::FlushHouse, hand_value(140, 14, 40, 4)),
+    (HandType::FlushFive, hand_value(160, 16, 50, 3)),
+];
+
+const fn hand_value(chips: u32, mult: u32, level_chips: u32, level_mult: u32) -> HandValue {
+    HandValue {
+        chips,
+        mult,
+        level_chips,
+        level_mult,
+    }
+}
+
 impl HandType {
-    pub fn get_value(&self, levels: &HandLevels) -> (u32, u32) {
-        let level = levels.get(self).unwrap_or(&0);
+    // Secret hands are hidden from the hand list and planet pool until played
+    pub fn is_secret(&self) -> bool {
+        matches!(
+            self,
+            HandType::FiveOfAKind | HandType::FlushHouse | HandType::FlushFive
+        )
+    }
 
+    // The hand whose level and base value this hand uses
+    pub fn get_level_type(&self) -> HandType {
         match self {
-            HandType::HighCard => (5 + (level * 5) as u32, 1 + (level * 1) as u32),
-            HandType::Pair => (10 + (level * 15) as u32, 2 + (level * 1) as u32),
-            HandType::TwoPair => (20 + (level * 20) as u32, 2 + (level * 1) as u32),
-            HandType::ThreeOfAKind => (30 + (level * 20) as u32, 3 + (level * 2) as u32),
-            HandType::Straight => (40 + (level * 30) as u32, 4 + (level * 3) as u32),
-            HandType::Flush => (35 + (level * 15) as u32, 4 + (level * 2) as u32),
-            HandType::FullHouse => (40 + (level * 25) as u32, 4 + (level * 2) as u32),
-            HandType::FourOfAKind => (60 + (level * 30) as u32, 7 + (level * 3) as u32),
-            HandType::StraightFlush => (100 + (level * 40) as u32, 8 + (level * 4) as u32),
-            HandType::RoyalFlush => (100 + (level * 40) as u32, 8 + (level * 4) as u32),
-            _ => (0, 0),
+            HandType::RoyalFlush => HandType::StraightFlush,
+            _ => self.clone(),
         }
     }
 
-    pub fn get_possible_hands(cards: Vec<&Card>) -> Vec<(HandType, Vec<&Card>)> {
-        let mut possible_hands: Vec<(HandType, Vec<&Card>)> = vec![];
-
-        // sorted_by_rank
-        let mut sorted_by_rank = cards.clone();
-        sorted_by_rank.sort_by(|a, b| b.rank.cmp(&a.rank));
+    pub fn get_base_value(&self) -> HandValue {
+        let level_type = self.get_level_type();
+        HAND_VALUES
+            .iter()
+            .find(|(hand_type, _)| *hand_type == level_type)
+            .map(|(_, value)| value.clone())
+            .unwrap()
+    }
 
-        let mut by_suits: HashMap<CardSuit, Vec<&Card>> = HashMap::new();
-        cards.iter().for_each(|card| {
-            by_suits
-                .entry(card.suit.clone())
-                .or_insert(vec![])
-                .push(card);
-        });
+    // Hands start at level 1, which is the base value
+    pub fn get_value(&self, level: u8) -> ChipsAndMult {
+        let levels = level.saturating_sub(1) as u32;
+        let value = self.get_base_value();
 
-        let mut by_ranks: HashMap<CardRank, Vec<&Card>> = HashMap::new();
-        cards.iter().for_each(|card| {
-            by_ranks
-                .entry(card.rank.clone())
-                .or_insert(vec![])
-                .push(card);
-        });
-
-        let pairs = by_ranks
-            .iter()
-            .filter(|(_, cards)| cards.len() == 2)
-            .map(|(_, cards)| cards)
-            .collect::<Vec<_>>();
+        ChipsAndMult::new(
+            (value.chips + levels * value.level_chips) as f64,
+            (value.mult + levels * value.level_mult) as f64,
+        )
+    }
 
-        let triples = by_ranks
-            .iter()
-            .filter(|(_, cards)| cards.len() == 3)
-            .map(|(_, cards)| cards)
-            .collect::<Vec<_>>();
+    // Whether a hand of this type also counts as `other`, e.g. for Jolly Joker
+    // a full house contains a pair
+    pub fn contains(&self, other: &HandType) -> bool {
+        let parts: &[HandType] = match self {
+            HandType::HighCard => &[],
+            HandType::Pair => &[],
+            HandType::TwoPair => &[HandType::Pair],
+            HandType::ThreeOfAKind => &[HandType::Pair],
+            HandType::Straight => &[],
+            HandType::Flush => &[],
+            HandType::FullHouse => &[HandType::Pair, HandType::TwoPair, HandType::ThreeOfAKind],
+            HandType::FourOfAKind => &[HandType::Pair, HandType::ThreeOfAKind],
+            HandType::StraightFlush | HandType::RoyalFlush => {
+                &[HandType::Straight, HandType::Flush]
+            }
+            HandType::FiveOfAKind => &[
+                HandType::Pair,
+                HandType::ThreeOfAKind,
+                HandType::FourOfAKind,
+            ],
+            HandType::FlushHouse => &[
+                HandType::Pair,
+                HandType::TwoPair,
+                HandType::ThreeOfAKind,
+                HandType::FullHouse,
+                HandType::Flush,
+            ],
+            HandType::FlushFive => &[
+                HandType::Pair,
+                HandType::ThreeOfAKind,
+                HandType::FourOfAKind,
+                HandType::FiveOfAKind,
+                HandType::Flush,
+            ],
+        };
+
+        self == other || *other == HandType::HighCard || parts.contains(other)
+    }
 
-        // Flushes
-        let flushes = by_suits
+    /// Each distinct play the given cards allow, classified under `rules`,
+    /// with its cards in the order given. Plays that differ only in their
+    /// kickers count once, as the one with the fewest cards: kickers never
+    /// score, while a card kept in hand still can.
+    pub fn get_possible_hands<'a>(
+        cards: Vec<&'a Card>,
+        rules: &HandRules,
+    ) -> Vec<(HandType, Vec<PlayedCard<'a>>)> {
+        // Subsets are classified packed, and only distinct plays are built
+        let packed = cards
             .iter()
-            .filter(|(_, cards)| cards.len() == 5)
-            .map(|(_, cards)| cards)
+            .map(|card| PackedCard::new(card, rules))
             .collect::<Vec<_>>();
-
-        // Flushes
-        for flush in flushes {
-            possible_hands.push((HandType::Flush, flush.clone()));
-        }
-
-        // Straights
-        let straight_cards_needed = 5;
-        if sorted_by_rank.len() >= straight_cards_needed {
-            let mut straight_cards = vec![];
-
-            for i in 0..sorted_by_rank.len() {
-                straight_cards = vec![sorted_by_rank[i]];
-
-                for j in i + 1..straight_cards_needed {
-                    let prev_card = sorted_by_rank[j - 1];
-                    let curr_card = sorted_by_rank[j];
-
-                    if curr_card.rank == prev_card.rank.get_straight_prev() {
-                        straight_cards.push(sorted_by_rank[j]);
-                    }
+        let mut subset_cards = Vec::with_capacity(MAX_PLAYED_CARDS);
+        let mut seen = HashSet::new();
+        let mut hands = vec![];
+
+        // Smaller subsets come first, so the first of each play has the fewest kickers
+        for size in 1..=cards.len().min(MAX_PLAYED_CARDS) {
+            for subset in (0..cards.len()).combinations(size) {
+                subset_cards.clear();
+                subset_cards.extend(subset.iter().map(|idx| packed[*idx]));
+                let Some((hand_type, scoring)) = classify(&subset_cards, rules) else {
+                    continue;
+                };
+
+                // The scoring cards' indices, which name the play along with its type
+                let is_scoring = |bit: usize| scoring & (1 << bit) != 0;
+                let mut key = [usize::MAX; MAX_PLAYED_CARDS];
+                let scoring_idxs = subset
+                    .iter()
+                    .enumerate()
+                    .filter(|(bit, _)| is_scoring(*bit));
+                for (slot, (_, idx)) in key.iter_mut().zip(scoring_idxs) {
+                    *slot = *idx;
                 }
-
-                if straight_cards.len() == straight_cards_needed {
-                    possible_hands.push((HandType::Straight, straight_cards.clone()));
+                if !seen.insert((hand_type.clone(), key)) {
+                    continue;
                 }
-            }
-        }
 
-        // Three of a kind
-        for triple in triples {
-            possible_hands.push((HandType::ThreeOfAKind, triple.clone()));
-        }
-
-        // Two Pair
-        let two_pairs = pairs.iter().combinations(2);
-        for two_pair in two_pairs {
-            let mut two_pair_cards = two_pair
-                .iter()
-                .map(|card| **card)
-                .flatten()
-                .map(|card| *card)
-                .collect::<Vec<_>>();
-
-            possible_hands.push((HandType::TwoPair, two_pair_cards));
-        }
-
-        // Pair
-        for pair in pairs {
-            possible_hands.push((HandType::Pair, pair.clone()));
-        }
-
-        // High card
-        for card in cards {
-            possible_hands.push((HandType::HighCard, vec![card]));
+                let played = subset
+                    .iter()
+                    .enumerate()
+                    .map(|(bit, idx)| PlayedCard {
+                        card: cards[*idx],
+                        is_scoring: is_scoring(bit),
+                    })
+                    .collect();
+                hands.push((hand_type, played));
+            }
         }
 
-        return possible_hands;
+        hands
     }
 
-    pub fn from_cards(cards: Vec<&Card>) -> (Self, Vec<&Card>) {
-        if cards.len() == 0 {
-            panic!("No cards");
+    /// Classifies played cards and returns every played card, in the order
+    /// they were played, marked with whether it scores. Checked in the game's
+    /// precedence order, so e.g. five cards of one suit and rank are a Flush
+    /// Five, not a Flush.
+    pub fn from_cards<'a>(
+        cards: Vec<&'a Card>,
+        rules: &HandRules,
+    ) -> Result<(Self, Vec<PlayedCard<'a>>), GameError> {
+        if cards.is_empty() {
+            return Err(GameError::NoCardsSelected);
         }
 
-        // Collect by suit
-        let mut by_suits: HashMap<CardSuit, Vec<&Card>> = HashMap::new();
-        cards.iter().for_each(|card| {
-            by_suits
-                .entry(card.suit.clone())
-                .or_insert(vec![])
-                .push(card);
-        });
+        // Stone cards score, but never help make the hand
+        let ranked = cards
+            .iter()
+            .filter(|card| card.has_rank())
+            .copied()
+            .collect::<Vec<_>>();
 
         let mut by_ranks: HashMap<CardRank, Vec<&Card>> = HashMap::new();
-        cards.iter().for_each(|card| {
+        ranked.iter().for_each(|card| {
             by_ranks
                 .entry(card.rank.clone())
                 .or_insert(vec![])
                 .push(card);
         });
 
-        // sorted_by_value
-        let mut sorted_by_value = cards.clone();
-        sorted_by_value.sort_by(|a, b| b.rank.cmp(&a.rank));
+        // Groups of exactly this many cards of one rank, highest rank first
+        let same = |count: usize| {
+            let mut groups = by_ranks
+                .iter()
+                .filter(|(_, cards)| cards.len() == count)
+                .collect::<Vec<_>>();
+            groups.sort_by(|a, b| b.0.cmp(a.0));
+            groups
+                .into_iter()
+                .map(|(_, cards)| cards.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let fives = same(5);
+        let fours = same(4);
+        let threes = same(3);
+        let pairs = same(2);
+        let flush = get_flush(&ranked, rules);
+        let straight = get_straight(&ranked, rules);
+
+        let (hand_type, parts): (HandType, Vec<Vec<&Card>>) = if let (Some(five), Some(_)) =
+            (fives.first(), &flush)
+        {
+            (HandType::FlushFive, vec![five.clone()])
+        } else if let (Some(three), Some(pair), Some(_)) = (threes.first(), pairs.first(), &flush) {
+            (HandType::FlushHouse, vec![three.clone(), pair.clone()])
+        } else if let Some(five) = fives.first() {
+            (HandType::FiveOfAKind, vec![five.clone()])
+        } else if let (Some(straight), Some(flush)) = (&straight, &flush) {
+            let royal = straight.iter().all(|card| card.rank >= CardRank::Ten);
+            let hand_type = if royal {
+                HandType::RoyalFlush
+            } else {
+                HandType::StraightFlush
+            };
+            (hand_type, vec![straight.clone(), flush.clone()])
+        } else if let Some(four) = fours.first() {
+            (HandType::FourOfAKind, vec![four.clone()])
+        } else if let (Some(three), Some(pair)) = (threes.first(), pairs.first()) {
+            (HandType::FullHouse, vec![three.clone(), pair.clone()])
+        } else if let Some(flush) = flush {
+            (HandType::Flush, vec![flush])
+        } else if let Some(straight) = straight {
+            (HandType::Straight, vec![straight])
+        } else if let Some(three) = threes.first() {
+            (HandType::ThreeOfAKind, vec![three.clone()])
+        } else if pairs.len() >= 2 {
+            (HandType::TwoPair, pairs[..2].to_vec())
+        } else if let Some(pair) = pairs.first() {
+            (HandType::Pair, vec![pair.clone()])
+        } else {
+            // First of the highest ranked cards, if any card has a rank
+            let mut high_card: Option<&Card> = None;
+            for card in ranked.iter() {
+                if high_card.is_none_or(|high_card| card.rank > high_card.rank) {
+                    high_card = Some(card);
+                }
+            }
+            (HandType::HighCard, vec![high_card.into_iter().collect()])
+        };
+
+        let played = cards
+            .iter()
+            .map(|card| PlayedCard {
+                card,
+                is_scoring: rules.all_cards_score
+                    || card.enhancement == Some(CardEnhancement::Stone)
+                    || parts.iter().flatten().any(|part| part.id == card.id),
+            })
+            .collect();
+
+        Ok((hand_type, played))
+    }
+}
+
+// Five or more cards of one suit, or four with Four Fingers
+fn get_flush<'a>(cards: &[&'a Card], rules: &HandRules) -> Option<Vec<&'a Card>> {
+    CARD_SUITS.iter().find_map(|suit| {
+        let suited = cards
+            .iter()
+            .filter(|card| rules.is_suit(card, suit))
+            .copied()
+            .collect::<Vec<_>>();
+
+        (suited.len() >= rules.get_hand_length()).then_some(suited)
+    })
+}
 
-        // Five of a kind
-        let five_of_a_kind_cards = by_ranks.iter().find(|(_, cards)| cards.len() == 5);
-        if let Some(five_of_a_kind_cards) = five_of_a_kind_cards {
-            return (HandType::FiveOfAKind, five_of_a_kind_cards.1.clone());
+// Five ranks in a row, or four with Four Fingers. Aces count high or low, but
+// a straight can't wrap around from King to Two. With Shortcut the run can
+// skip a single missing rank at a time.
+fn get_straight<'a>(cards: &[&'a Card], rules: &HandRules) -> Option<Vec<&'a Card>> {
+    // Index 0 is a low Ace, 1..=13 are Two through Ace
+    let mut has_rank = [false; 14];
+    for card in cards.iter() {
+        has_rank[card.rank.clone() as usize + 1] = true;
+        if card.rank == CardRank::Ace {
+            has_rank[0] = true;
         }
+    }
 
-        // Four of a kind
-        let four_of_a_kind_cards = by_ranks.iter().find(|(_, cards)| cards.len() == 4);
-        if let Some(four_of_a_kind_cards) = four_of_a_kind_cards {
-            return (HandType::FourOfAKind, four_of_a_kind_cards.1.clone());
+    let mut run = 0;
+    let mut run_ranks = vec![];
+    let mut skipped = false;
+    let mut found = false;
+
+    for (idx, has) in has_rank.iter().enumerate() {
+        if *has {
+            run += 1;
+            run_ranks.push(idx);
+            skipped = false;
+        } else if rules.straight_gaps && !skipped && idx != has_rank.len() - 1 {
+            skipped = true;
+        } else if found {
+            break;
+        } else {
+            run = 0;
+            run_ranks.clear();
+            skipped = false;
         }
 
-        // Flush
-        let flush_cards = by_suits.iter().find(|(_, cards)| cards.len() == 5);
-        if let Some(flush_cards) = flush_cards {
-            return (HandType::Flush, flush_cards.1.clone());
+        if run >= rules.get_hand_length() {
+            found = true;
         }
+    }
+
+    if !found {
+        return None;
+    }
 
-        // Straight
-        let straight_cards_needed = 5;
-        if sorted_by_value.len() >= straight_cards_needed {
-            let mut straight_cards = vec![];
+    let in_run = |card: &Card| {
+        run_ranks.contains(&(card.rank.clone() as usize + 1))
+            || (card.rank == CardRank::Ace && run_ranks.contains(&0))
+    };
 
-            for i in 0..sorted_by_value.len() {
-                straight_cards = vec![sorted_by_value[i]];
+    Some(cards.iter().filter(|card| in_run(card)).copied().collect())
+}
 
-                for j in i + 1..straight_cards_needed {
-                    let prev_card = sorted_by_value[j - 1];
-                    let curr_card = sorted_by_value[j];
+impl GameState {
+    pub fn get_hand_stats(&self, hand_type: &HandType) -> &HandStats {
+        &self.hand_stats[&hand_type.get_level_type()]
+    }
 
-                    if curr_card.rank == prev_card.rank.get_straight_prev() {
-                        straight_cards.push(sorted_by_value[j]);
-                    }
-                }
+    pub fn get_hand_level(&self, hand_type: &HandType) -> u8 {
+        self.get_hand_stats(hand_type).level
+    }
 
-                if straight_cards.len() == straight_cards_needed {
-                    return (HandType::Straight, straight_cards);
-                }
+    // For Telescope and Obelisk. A tie goes to the better hand, and nothing
+    // counts until a hand has been played
+    pub fn get_most_played_hand(&self) -> Option<HandType> {
+        let mut most_played: Option<HandType> = None;
+        for hand_type in HAND_TYPES.iter().rev() {
+            if hand_type.get_level_type() != *hand_type {
+                continue;
             }
-        }
 
-        // At least three of a kind; maybe full house
-        let triple_cards = by_ranks.iter().find(|(_, cards)| cards.len() == 3);
-        let first_pair_cards = by_ranks.iter().find(|(_, cards)| cards.len() == 2);
-        if let Some(first_triple_cards) = triple_cards {
-            // Full house
-            if let Some(full_house_pair_cards) = first_pair_cards {
-                let mut full_house_cards = first_triple_cards.1.clone();
-                full_house_cards.extend(full_house_pair_cards.1.clone());
-                return (HandType::FullHouse, full_house_cards);
+            let played = self.get_hand_stats(hand_type).played;
+            let most = most_played
+                .as_ref()
+                .map_or(0, |most| self.get_hand_stats(most).played);
+            if played > most {
+                most_played = Some(hand_type.clone());
             }
-
-            // Three of a kind
-            return (HandType::ThreeOfAKind, first_triple_cards.1.clone());
         }
 
-        // At least one pair; maybe two pair
-        if let Some(first_pair_cards) = first_pair_cards {
-            let second_pair_cards = by_ranks
-                .iter()
-                .find(|(rank, cards)| cards.len() == 2 && *rank != first_pair_cards.0);
+        most_played
+    }
 
-            // Two pair
-            if let Some(second_pair_cards) = second_pair_cards {
-                let mut two_pair_cards = first_pair_cards.1.clone();
-                two_pair_cards.extend(second_pair_cards.1.clone());
-                return (HandType::TwoPair, two_pair_cards);
-            }
+    pub(crate) fn record_hand_played(&mut self, hand_type: &HandType) {
+        let stats = self
+            .hand_stats
+            .get_mut(&hand_type.get_level_type())
+            .unwrap();
+        stats.played += 1;
+        stats.played_this_round += 1;
 
-            // Pair
-            return (HandType::Pair, first_pair_cards.1.clone());
-        }
+        self.last_hand_played = Some(hand_type.clone());
+    }
 
-        // If nothing else, return high card
-        (Self::HighCard, vec![sorted_by_value.first().unwrap()])
+    pub(crate) fn reset_round_hand_stats(&mut self) {
+        for stats in self.hand_stats.values_mut() {
+            stats.played_this_round = 0;
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::vec;
-
     use super::*;
+    use crate::card::{CardId, CardIdAllocator};
+
+    fn new_cards(cards: &[(CardRank, CardSuit)]) -> Vec<Card> {
+        let mut ids = CardIdAllocator::new();
+        cards
+            .iter()
+            .map(|(rank, suit)| ids.new_card(rank.clone(), suit.clone()))
+            .collect()
+    }
+
+    fn classify(cards: &[Card]) -> (HandType, Vec<CardId>) {
+        classify_with(cards, &HandRules::default())
+    }
+
+    // The hand type and the ids of the cards that score
+    fn classify_with(cards: &[Card], rules: &HandRules) -> (HandType, Vec<CardId>) {
+        let (hand_type, played) = HandType::from_cards(cards.iter().collect(), rules).unwrap();
+        let scoring = played
+            .iter()
+            .filter(|played| played.is_scoring)
+            .map(|played| played.card.id)
+            .collect();
+        (hand_type, scoring)
+    }
 
     #[test]
     fn test_hand_type_full_house() {
-        let cards = vec![
-            Card::new(CardRank::Two, CardSuit::Spades),
-            Card::new(CardRank::Two, CardSuit::Hearts),
-            Card::new(CardRank::Two, CardSuit::Clubs),
-            Card::new(CardRank::Ace, CardSuit::Spades),
-            Card::new(CardRank::Ace, CardSuit::Hearts),
+        let mut ids = CardIdAllocator::new();
+        let cards = [
+            ids.new_card(CardRank::Two, CardSuit::Spades),
+            ids.new_card(CardRank::Two, CardSuit::Hearts),
+            ids.new_card(CardRank::Two, CardSuit::Clubs),
+            ids.new_card(CardRank::Ace, CardSuit::Spades),
+            ids.new_card(CardRank::Ace, CardSuit::Hearts),
         ];
-        let (hand_type, cards) = HandType::from_cards(cards.iter().collect());
+        let (hand_type, _) =
+            HandType::from_cards(cards.iter().collect(), &HandRules::default()).unwrap();
         assert_eq!(hand_type, HandType::FullHouse);
     }
     #[test]
     fn test_hand_type_flush() {
-        let suit = CardSuit::get_rand();
-        let cards = vec![
-            Card::new(CardRank::Two, CardSuit::Diamonds),
-            Card::new(CardRank::Five, CardSuit::Diamonds),
-            Card::new(CardRank::Queen, CardSuit::Diamonds),
-            Card::new(CardRank::Ace, CardSuit::Diamonds),
-            Card::new(CardRank::King, CardSuit::Diamonds),
+        let mut ids = CardIdAllocator::new();
+        let cards = [
+            ids.new_card(CardRank::Two, CardSuit::Diamonds),
+            ids.new_card(CardRank::Five, CardSuit::Diamonds),
+            ids.new_card(CardRank::Queen, CardSuit::Diamonds),
+            ids.new_card(CardRank::Ace, CardSuit::Diamonds),
+            ids.new_card(CardRank::King, CardSuit::Diamonds),
         ];
-        let (hand_type, cards) = HandType::from_cards(cards.iter().collect());
+        let (hand_type, _) =
+            HandType::from_cards(cards.iter().collect(), &HandRules::default()).unwrap();
         assert_eq!(hand_type, HandType::Flush);
     }
 
     #[test]
     fn test_hand_type_straight() {
-        let cards = vec![
-            Card::new(CardRank::Two, CardSuit::Spades),
-            Card::new(CardRank::Three, CardSuit::Hearts),
-            Card::new(CardRank::Four, CardSuit::Clubs),
-            Card::new(CardRank::Five, CardSuit::Diamonds),
-            Card::new(CardRank::Six, CardSuit::Spades),
+        let mut ids = CardIdAllocator::new();
+        let cards = [
+            ids.new_card(CardRank::Two, CardSuit::Spades),
+            ids.new_card(CardRank::Three, CardSuit::Hearts),
+            ids.new_card(CardRank::Four, CardSuit::Clubs),
+            ids.new_card(CardRank::Five, CardSuit::Diamonds),
+            ids.new_card(CardRank::Six, CardSuit::Spades),
         ];
-        let (hand_type, cards) = HandType::from_cards(cards.iter().collect());
+        let (hand_type, _) =
+            HandType::from_cards(cards.iter().collect(), &HandRules::default()).unwrap();
         assert_eq!(hand_type, HandType::Straight);
     }
 
     #[test]
     fn test_hand_type_three_of_a_kind() {
-        let cards = vec![
-            Card::new(CardRank::Ace, CardSuit::Spades),
-            Card::new(CardRank::Ace, CardSuit::Hearts),
-            Card::new(CardRank::Ace, CardSuit::Clubs),
+        let mut ids = CardIdAllocator::new();
+        let cards = [
+            ids.new_card(CardRank::Ace, CardSuit::Spades),
+            ids.new_card(CardRank::Ace, CardSuit::Hearts),
+            ids.new_card(CardRank::Ace, CardSuit::Clubs),
         ];
-        let (hand_type, cards) = HandType::from_cards(cards.iter().collect());
+        let (hand_type, _) =
+            HandType::from_cards(cards.iter().collect(), &HandRules::default()).unwrap();
         assert_eq!(hand_type, HandType::ThreeOfAKind);
     }
     #[test]
     fn test_hand_type_two_pair() {
-        let cards = vec![
-            Card::new(CardRank::Ace, CardSuit::Spades),
-            Card::new(CardRank::Ace, CardSuit::Hearts),
-            Card::new(CardRank::Two, CardSuit::Clubs),
-            Card::new(CardRank::Two, CardSuit::Diamonds),
+        let mut ids = CardIdAllocator::new();
+        let cards = [
+            ids.new_card(CardRank::Ace, CardSuit::Spades),
+            ids.new_card(CardRank::Ace, CardSuit::Hearts),
+            ids.new_card(CardRank::Two, CardSuit::Clubs),
+            ids.new_card(CardRank::Two, CardSuit::Diamonds),
         ];
-        let (hand_type, cards) = HandType::from_cards(cards.iter().collect());
+        let (hand_type, _) =
+            HandType::from_cards(cards.iter().collect(), &HandRules::default()).unwrap();
         assert_eq!(hand_type, HandType::TwoPair);
     }
     #[test]
     fn test_hand_type_pair() {
-        let cards = vec![
-            Card::new(CardRank::Ace, CardSuit::Spades),
-            Card::new(CardRank::Ace, CardSuit::Hearts),
+        let mut ids = CardIdAllocator::new();
+        let cards = [
+            ids.new_card(CardRank::Ace, CardSuit::Spades),
+            ids.new_card(CardRank::Ace, CardSuit::Hearts),
         ];
-        let (hand_type, cards) = HandType::from_cards(cards.iter().collect());
+        let (hand_type, _) =
+            HandType::from_cards(cards.iter().collect(), &HandRules::default()).unwrap();
         assert_eq!(hand_type, HandType::Pair);
     }
+    #[test]
+    fn test_hand_type_no_cards() {
+        assert_eq!(
+            HandType::from_cards(vec![], &HandRules::default()).err(),
+            Some(GameError::NoCardsSelected)
+        );
+    }
+
     #[test]
     fn test_hand_type_high_card() {
-        let cards = vec![Card::new(CardRank::Ace, CardSuit::Spades)];
-        let (hand_type, cards) = HandType::from_cards(cards.iter().collect());
+        let mut ids = CardIdAllocator::new();
+        let cards = [ids.new_card(CardRank::Ace, CardSuit::Spades)];
+        let (hand_type, _) =
+            HandType::from_cards(cards.iter().collect(), &HandRules::default()).unwrap();
         assert_eq!(hand_type, HandType::HighCard);
     }
+
+    #[test]
+    fn test_hand_type_secret_hands() {
+        use CardRank::*;
+        use CardSuit::*;
+
+        let flush_five = new_cards(&[
+            (Nine, Clubs),
+            (Nine, Clubs),
+            (Nine, Clubs),
+            (Nine, Clubs),
+            (Nine, Clubs),
+        ]);
+        assert_eq!(
+            classify(&flush_five),
+            (HandType::FlushFive, vec![0, 1, 2, 3, 4])
+        );
+
+        let five = new_cards(&[
+            (Nine, Clubs),
+            (Nine, Hearts),
+            (Nine, Clubs),
+            (Nine, Clubs),
+            (Nine, Clubs),
+        ]);
+        assert_eq!(classify(&five).0, HandType::FiveOfAKind);
+
+        let flush_house = new_cards(&[
+            (Nine, Clubs),
+            (Four, Clubs),
+            (Nine, Clubs),
+            (Four, Clubs),
+            (Nine, Clubs),
+        ]);
+        assert_eq!(
+            classify(&flush_house),
+            (HandType::FlushHouse, vec![0, 1, 2, 3, 4])
+        );
+    }
+
+    #[test]
+    fn test_hand_type_straight_flushes() {
+        use CardRank::*;
+        use CardSuit::*;
+
+        let straight_flush = new_cards(&[
+            (Nine, Hearts),
+            (Ten, Hearts),
+            (Jack, Hearts),
+            (Queen, Hearts),
+            (King, Hearts),
+        ]);
+        assert_eq!(classify(&straight_flush).0, HandType::StraightFlush);
+
+        let royal_flush = new_cards(&[
+            (Ace, Hearts),
+            (Ten, Hearts),
+            (Jack, Hearts),
+            (Queen, Hearts),
+            (King, Hearts),
+        ]);
+        assert_eq!(classify(&royal_flush).0, HandType::RoyalFlush);
+
+        // Ace-low is a straight flush, not a royal one
+        let steel_wheel = new_cards(&[
+            (Ace, Hearts),
+            (Two, Hearts),
+            (Three, Hearts),
+            (Four, Hearts),
+            (Five, Hearts),
+        ]);
+        assert_eq!(classify(&steel_wheel).0, HandType::StraightFlush);
+    }
+
+    #[test]
+    fn test_hand_type_straight_edges() {
+        use CardRank::*;
+        use CardSuit::*;
+
+        let ace_low = new_cards(&[
+            (Three, Spades),
+            (Ace, Hearts),
+            (Five, Clubs),
+            (Two, Diamonds),
+            (Four, Spades),
+        ]);
+        assert_eq!(
+            classify(&ace_low),
+            (HandType::Straight, vec![0, 1, 2, 3, 4])
+        );
+
+        let ace_high = new_cards(&[
+            (Ace, Spades),
+            (King, Hearts),
+            (Queen, Clubs),
+            (Jack, Diamonds),
+            (Ten, Spades),
+        ]);
+        assert_eq!(classify(&ace_high).0, HandType::Straight);
+
+        // Straights don't wrap around
+        let wrapped = new_cards(&[
+            (Queen, Spades),
+            (King, Hearts),
+            (Ace, Clubs),
+            (Two, Diamonds),
+            (Three, Spades),
+        ]);
+        assert_eq!(classify(&wrapped), (HandType::HighCard, vec![2]));
+
+        let four_cards = new_cards(&[
+            (Two, Spades),
+            (Three, Hearts),
+            (Four, Clubs),
+            (Five, Diamonds),
+        ]);
+        assert_eq!(classify(&four_cards), (HandType::HighCard, vec![3]));
+    }
+
+    #[test]
+    fn test_hand_type_kickers_are_left_out() {
+        use CardRank::*;
+        use CardSuit::*;
+
+        let four = new_cards(&[
+            (Six, Spades),
+            (Jack, Hearts),
+            (Six, Hearts),
+            (Six, Clubs),
+            (Six, Diamonds),
+        ]);
+        assert_eq!(classify(&four), (HandType::FourOfAKind, vec![0, 2, 3, 4]));
+
+        let two_pair = new_cards(&[
+            (Six, Spades),
+            (Jack, Hearts),
+            (Ace, Hearts),
+            (Six, Clubs),
+            (Jack, Diamonds),
+        ]);
+        assert_eq!(classify(&two_pair), (HandType::TwoPair, vec![0, 1, 3, 4]));
+
+        let three = new_cards(&[(Two, Spades), (Two, Hearts), (Ace, Hearts), (Two, Clubs)]);
+        assert_eq!(classify(&three), (HandType::ThreeOfAKind, vec![0, 1, 3]));
+
+        let high_card = new_cards(&[(Two, Spades), (King, Hearts), (Nine, Clubs), (Ten, Spades)]);
+        assert_eq!(classify(&high_card), (HandType::HighCard, vec![1]));
+    }
+
+    #[test]
+    fn test_hand_type_flush_beats_pair() {
+        use CardRank::*;
+        use CardSuit::*;
+
+        let flush = new_cards(&[
+            (Two, Spades),
+            (Two, Spades),
+            (Nine, Spades),
+            (Jack, Spades),
+            (Four, Spades),
+        ]);
+        assert_eq!(classify(&flush), (HandType::Flush, vec![0, 1, 2, 3, 4]));
+
+        let four_suited =
+            new_cards(&[(Two, Spades), (Two, Spades), (Nine, Spades), (Jack, Spades)]);
+        assert_eq!(classify(&four_suited).0, HandType::Pair);
+    }
+
+    #[test]
+    fn test_hand_rules_four_fingers() {
+        use CardRank::*;
+        use CardSuit::*;
+
+        let rules = HandRules {
+            four_card_hands: true,
+            ..Default::default()
+        };
+
+        let flush = new_cards(&[
+            (Two, Hearts),
+            (Nine, Hearts),
+            (King, Spades),
+            (Jack, Hearts),
+            (Four, Hearts),
+        ]);
+        assert_eq!(
+            classify_with(&flush, &rules),
+            (HandType::Flush, vec![0, 1, 3, 4])
+        );
+
+        let straight = new_cards(&[
+            (Two, Spades),
+            (Three, Hearts),
+            (Four, Clubs),
+            (Five, Diamonds),
+        ]);
+        assert_eq!(classify_with(&straight, &rules).0, HandType::Straight);
+
+        let straight_flush = new_cards(&[
+            (Two, Spades),
+            (Three, Spades),
+            (Four, Spades),
+            (Five, Spades),
+            (King, Hearts),
+        ]);
+        assert_eq!(
+            classify_with(&straight_flush, &rules),
+            (HandType::StraightFlush, vec![0, 1, 2, 3])
+        );
+    }
+
+    #[test]
+    fn test_hand_rules_shortcut() {
+        use CardRank::*;
+        use CardSuit::*;
+
+        let rules = HandRules {
+            straight_gaps: true,
+            ..Default::default()
+        };
+
+        let gapped = new_cards(&[
+            (Two, Spades),
+            (Four, Hearts),
+            (Six, Clubs),
+            (Seven, Diamonds),
+            (Eight, Spades),
+        ]);
+        assert_eq!(classify(&gapped).0, HandType::HighCard);
+        assert_eq!(classify_with(&gapped, &rules).0, HandType::Straight);
+
+        // Only one rank can be skipped at a time
+        let wide = new_cards(&[
+            (Two, Spades),
+            (Five, Hearts),
+            (Six, Clubs),
+            (Seven, Diamonds),
+            (Eight, Spades),
+        ]);
+        assert_eq!(classify_with(&wide, &rules).0, HandType::HighCard);
+    }
+
+    #[test]
+    fn test_hand_rules_suits() {
+        use CardRank::*;
+        use CardSuit::*;
+
+        let mixed = new_cards(&[
+            (Two, Hearts),
+            (Nine, Diamonds),
+            (King, Hearts),
+            (Jack, Diamonds),
+            (Four, Hearts),
+        ]);
+        assert_eq!(classify(&mixed).0, HandType::HighCard);
+
+        let smeared = HandRules {
+            merged_suits: true,
+            ..Default::default()
+        };
+        assert_eq!(classify_with(&mixed, &smeared).0, HandType::Flush);
+
+        let mut wild = new_cards(&[
+            (Two, Clubs),
+            (Nine, Clubs),
+            (King, Hearts),
+            (Jack, Clubs),
+            (Four, Clubs),
+        ]);
+        wild[2].enhancement = Some(CardEnhancement::Wild);
+        assert_eq!(classify(&wild).0, HandType::HighCard);
+
+        let rules = HandRules {
+            wild_suits: true,
+            ..Default::default()
+        };
+        assert_eq!(classify_with(&wild, &rules).0, HandType::Flush);
+    }
+
+    #[test]
+    fn test_hand_type_marks_scoring_cards() {
+        use CardRank::*;
+        use CardSuit::*;
+
+        let mut cards = new_cards(&[(Six, Spades), (Jack, Hearts), (Six, Hearts), (Two, Clubs)]);
+
+        let (_, played) =
+            HandType::from_cards(cards.iter().collect(), &HandRules::default()).unwrap();
+        let flags = played
+            .iter()
+            .map(|played| played.is_scoring)
+            .collect::<Vec<_>>();
+        assert_eq!(flags, vec![true, false, true, false]);
+        assert_eq!(
+            played
+                .iter()
+                .map(|played| played.card.id)
+                .collect::<Vec<_>>(),
+            vec![0, 1, 2, 3]
+        );
+
+        cards[3].enhancement = Some(CardEnhancement::Stone);
+        assert_eq!(classify(&cards), (HandType::Pair, vec![0, 2, 3]));
+
+        let splash = HandRules {
+            all_cards_score: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            classify_with(&cards, &splash),
+            (HandType::Pair, vec![0, 1, 2, 3])
+        );
+    }
+
+    #[test]
+    fn test_possible_hands() {
+        use CardRank::*;
+        use CardSuit::*;
+
+        let cards = new_cards(&[
+            (Five, Hearts),
+            (Six, Hearts),
+            (Seven, Hearts),
+            (Eight, Hearts),
+            (Nine, Hearts),
+            (Nine, Spades),
+            (Nine, Clubs),
+            (Nine, Diamonds),
+        ]);
+
+        let possible = HandType::get_possible_hands(cards.iter().collect(), &HandRules::default());
+        // Of the 218 subsets of 1 to 5 cards, 8 High Cards, 6 Pairs, 4 Three
+        // of a Kinds, a Four of a Kind, 3 Straights and a Straight Flush differ
+        // in more than their kickers
+        assert_eq!(possible.len(), 23);
+
+        let found = |hand_type: HandType| {
+            possible
+                .iter()
+                .filter(|(found, _)| *found == hand_type)
+                .count()
+        };
+        assert_eq!(found(HandType::StraightFlush), 1);
+        // The four nines, whatever card is played next to them
+        assert_eq!(found(HandType::FourOfAKind), 1);
+        let four = possible
+            .iter()
+            .find(|(found, _)| *found == HandType::FourOfAKind)
+            .unwrap();
+        assert_eq!(four.1.len(), 4);
+        assert_eq!(found(HandType::FullHouse), 0);
+        assert!(found(HandType::ThreeOfAKind) > 0);
+        assert!(possible
+            .iter()
+            .all(|(_, played)| played.len() <= MAX_PLAYED_CARDS));
+
+        // The packed evaluator agrees with `from_cards` on every play
+        for (hand_type, played) in possible.iter() {
+            let cards = played.iter().map(|played| played.card).collect();
+            let (expected, expected_played) =
+                HandType::from_cards(cards, &HandRules::default()).unwrap();
+            assert_eq!(*hand_type, expected);
+            assert!(played
+                .iter()
+                .zip(expected_played.iter())
+                .all(|(a, b)| a.is_scoring == b.is_scoring));
+        }
+    }
+
+    #[test]
+    fn test_hand_values() {
+        assert_eq!(
+            HandType::FlushFive.get_value(1),
+            ChipsAndMult::new(160.0, 16.0)
+        );
+        assert_eq!(
+            HandType::RoyalFlush.get_value(3),
+            ChipsAndMult::new(180.0, 16.0)
+        );
+
+        for hand_type in HAND_TYPES.iter() {
+            let value = hand_type.get_base_value();
+            assert!(value.chips > 0 && value.mult > 0, "{:?}", hand_type);
+        }
+    }
 }
@@ -1,6 +1,16 @@
+use rand::{seq::SliceRandom, Rng};
+
 use crate::card::CardEdition;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JokerRarity {
+    Common,
+    Uncommon,
+    Rare,
+    Legendary,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum JokerType {
     // Common
     Joker,
@@ -43,6 +53,7 @@ pub enum JokerType {
     IceCream(u8), // Chips, Starting 100, -5 each hand.
     Splash,
     Blue,
+    Juggler,
 
     // Uncommon
     Stencil,
@@ -63,14 +74,233 @@ pub enum JokerType {
     SixthSense,
     Constellation,
     Hiker,
+    Shortcut,
+    Smeared,
+    Troubadour,
 
     // Rare
-    Dna, // Legendary
+    Dna,
+
+    // Legendary
+    Canio(u8), // Face cards destroyed
+    Triboulet,
+    Yorick(u16), // Cards discarded
+    Chicot,
+    Perkeo,
+}
+
+// Every joker as it appears when first created
+pub const JOKERS: [JokerType; 65] = [
+    JokerType::Joker,
+    JokerType::Greedy,
+    JokerType::Lusty,
+    JokerType::Wrathful,
+    JokerType::Gluttonous,
+    JokerType::Jolly,
+    JokerType::Zany,
+    JokerType::Mad,
+    JokerType::Crazy,
+    JokerType::Droll,
+    JokerType::Sly,
+    JokerType::Wily,
+    JokerType::Clever,
+    JokerType::Devious,
+    JokerType::Crafty,
+    JokerType::Half,
+    JokerType::CreditCard,
+    JokerType::Banner,
+    JokerType::MysticSummit,
+    JokerType::EightBall,
+    JokerType::Misprint,
+    JokerType::RaisedFist,
+    JokerType::ChaosTheClown,
+    JokerType::ScaryFace,
+    JokerType::Abstract,
+    JokerType::DelayedGratification,
+    JokerType::GrosMichel,
+    JokerType::EvenSteven,
+    JokerType::OddTodd,
+    JokerType::Scholar,
+    JokerType::BusinessCard,
+    JokerType::SuperNova,
+    JokerType::RideTheBus(0),
+    JokerType::Egg,
+    JokerType::Runner(0),
+    JokerType::IceCream(100),
+    JokerType::Splash,
+    JokerType::Blue,
+    JokerType::Juggler,
+    JokerType::Stencil,
+    JokerType::FourFingers,
+    JokerType::Mime,
+    JokerType::CeremonialDagger,
+    JokerType::Marble,
+    JokerType::Loyalty(5),
+    JokerType::Dusk,
+    JokerType::Fibonacci,
+    JokerType::Steel,
+    JokerType::Hack,
+    JokerType::Pareidolia,
+    JokerType::Space,
+    JokerType::Burglar,
+    JokerType::Blackboard,
+    JokerType::SixthSense,
+    JokerType::Constellation,
+    JokerType::Hiker,
+    JokerType::Shortcut,
+    JokerType::Smeared,
+    JokerType::Troubadour,
+    JokerType::Dna,
+    JokerType::Canio(0),
+    JokerType::Triboulet,
+    JokerType::Yorick(0),
+    JokerType::Chicot,
+    JokerType::Perkeo,
+];
+
+impl JokerType {
+    pub fn get_rand(rarity: JokerRarity, rng: &mut impl Rng) -> Option<Self> {
+        JOKERS
+            .iter()
+            .filter(|joker| joker.get_rarity() == rarity)
+            .collect::<Vec<_>>()
+            .choose(rng)
+            .map(|joker| (*joker).clone())
+    }
+
+    // Same joker, ignoring any counter it carries
+    pub fn is_same(&self, other: &JokerType) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    pub fn get_rarity(&self) -> JokerRarity {
+        match self {
+            JokerType::Stencil
+            | JokerType::FourFingers
+            | JokerType::Mime
+            | JokerType::CeremonialDagger
+            | JokerType::Marble
+            | JokerType::Loyalty(_)
+            | JokerType::Dusk
+            | JokerType::Fibonacci
+            | JokerType::Steel
+            | JokerType::Hack
+            | JokerType::Pareidolia
+            | JokerType::Space
+            | JokerType::Burglar
+            | JokerType::Blackboard
+            | JokerType::SixthSense
+            | JokerType::Constellation
+            | JokerType::Hiker
+            | JokerType::Shortcut
+            | JokerType::Smeared
+            | JokerType::Troubadour => JokerRarity::Uncommon,
+
+            JokerType::Dna => JokerRarity::Rare,
+
+            JokerType::Canio(_)
+            | JokerType::Triboulet
+            | JokerType::Yorick(_)
+            | JokerType::Chicot
+            | JokerType::Perkeo => JokerRarity::Legendary,
+
+            _ => JokerRarity::Common,
+        }
+    }
+
+    pub fn get_cost(&self) -> u8 {
+        match self {
+            JokerType::Joker => 2,
+            JokerType::Greedy => 5,
+            JokerType::Lusty => 5,
+            JokerType::Wrathful => 5,
+            JokerType::Gluttonous => 5,
+            JokerType::Jolly => 3,
+            JokerType::Zany => 4,
+            JokerType::Mad => 4,
+            JokerType::Crazy => 4,
+            JokerType::Droll => 4,
+
+            JokerType::Sly => 3,
+            JokerType::Wily => 4,
+            JokerType::Clever => 4,
+            JokerType::Devious => 4,
+            JokerType::Crafty => 4,
+            JokerType::Half => 5,
+
+            JokerType::CreditCard => 1,
+            JokerType::Banner => 5,
+            JokerType::MysticSummit => 5,
+            JokerType::EightBall => 5,
+            JokerType::Misprint => 4,
+            JokerType::RaisedFist => 5,
+            JokerType::ChaosTheClown => 4,
+            JokerType::ScaryFace => 4,
+            JokerType::Abstract => 4,
+            JokerType::DelayedGratification => 4,
+            JokerType::GrosMichel => 5,
+            JokerType::EvenSteven => 4,
+            JokerType::OddTodd => 4,
+            JokerType::Scholar => 4,
+            JokerType::BusinessCard => 4,
+            JokerType::SuperNova => 5,
+            JokerType::RideTheBus(_) => 6,
+            JokerType::Egg => 4,
+            JokerType::Runner(_) => 5,
+            JokerType::IceCream(_) => 5,
+            JokerType::Splash => 3,
+            JokerType::Blue => 5,
+            JokerType::Juggler => 4,
+
+            JokerType::Stencil => 8,
+            JokerType::FourFingers => 7,
+            JokerType::Mime => 5,
+
+            JokerType::CeremonialDagger => 6,
+            JokerType::Marble => 6,
+            JokerType::Loyalty(_) => 5,
+            JokerType::Dusk => 5,
+            JokerType::Fibonacci => 8,
+            JokerType::Steel => 7,
+            JokerType::Hack => 6,
+            JokerType::Pareidolia => 5,
+            JokerType::Space => 5,
+            JokerType::Burglar => 6,
+            JokerType::Blackboard => 6,
+            JokerType::SixthSense => 6,
+            JokerType::Constellation => 6,
+            JokerType::Hiker => 5,
+            JokerType::Shortcut => 7,
+            JokerType::Smeared => 7,
+            JokerType::Troubadour => 6,
+
+            JokerType::Dna => 8,
+
+            JokerType::Canio(_) => 20,
+            JokerType::Triboulet => 20,
+            JokerType::Yorick(_) => 20,
+            JokerType::Chicot => 20,
+            JokerType::Perkeo => 20,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct JokerCard {
     pub joker: JokerType,
+    // Sell value; some jokers (e.g. Egg) raise it over the run
     pub value: u8,
     pub edition: CardEdition,
 }
+
+impl JokerCard {
+    pub fn new(joker: JokerType) -> Self {
+        let value = (joker.get_cost() / 2).max(1);
+
+        Self {
+            joker,
+            value,
+            edition: CardEdition::Base,
+        }
+    }
+}
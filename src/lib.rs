@@ -1,31 +1,46 @@
+pub mod action;
 pub mod ante;
 pub mod blind;
 pub mod booster;
 pub mod card;
 pub mod consumable;
+pub mod deck;
+pub mod error;
+pub mod fast_hand;
 pub mod hand;
 pub mod joker;
+pub mod rng;
+pub mod round;
+pub mod score;
+pub mod scoring;
+pub mod seal;
+pub mod shop;
 pub mod stake;
 mod utils;
 pub mod voucher;
 
 use std::{
-    clone,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt::Debug,
-    sync::atomic::{AtomicU32, Ordering},
 };
 
-use ante::get_base_score;
+use ante::{get_base_score, WINNING_ANTE};
 use blind::{Blind, BossBlind};
+use booster::OpenPack;
 use card::{
-    debug_cards, Card, CardEnhancement, CardRank, CardSuit, CARD_RANKS, CARD_SUITS, FACE_CARDS,
+    debug_cards, Card, CardId, CardIdAllocator, CardRank, CardSuit, CARD_RANKS, CARD_SUITS,
+    FACE_CARDS,
 };
-use consumable::{Consumable, TarotCard};
-use hand::HandType;
-use itertools::Itertools;
-use joker::JokerCard;
-use rand::seq::SliceRandom;
+use consumable::{Consumable, ConsumableCard, TarotCard};
+use deck::Deck;
+use error::GameError;
+use hand::{HandStats, HandType, HAND_TYPES, MAX_PLAYED_CARDS};
+use joker::{JokerCard, JokerType};
+use rng::{GameRng, GameSeed, RngStream};
+use round::CashOut;
+use score::Score;
+use scoring::{Chance, ScoreTrace};
+use shop::GameShopState;
 use stake::GameStake;
 use voucher::Voucher;
 use wasm_bindgen::prelude::*;
@@ -60,10 +75,18 @@ pub enum GameStartingDeck {
     Erratic,
 }
 
+/// Where the run is in the round lifecycle. Each round goes
+/// `BlindSelect -> Playing -> CashOut -> Shop` and back to `BlindSelect` for
+/// the next blind, until a blind is lost.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum GamePhase {
+    // Choosing to play or skip the blind on deck
+    BlindSelect,
     Playing,
+    // Blind beaten, payout not collected yet
+    CashOut,
     Shop,
+    GameOver,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -72,140 +95,93 @@ pub enum HandResult {
     Lose,
 }
 
-#[derive(Clone, Debug)]
-pub struct ChipsAndMult {
-    pub chips: u32,
-    pub mult: u32,
+// Why cards are being drawn, as some bosses deal them face down
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum DrawReason {
+    RoundStart,
+    HandPlayed,
+    Discarded,
+}
+
+/// A hand that was played: how it scored, step by step, and whether it ended
+/// the blind.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlayedHand {
+    pub trace: ScoreTrace,
+    pub result: Option<HandResult>,
+}
+
+/// Set once the run is decided. `Won` is set the first time the ante 8
+/// finisher boss is beaten; the run then carries on in endless mode, and
+/// losing a blind after that doesn't take the win away.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GameOutcome {
+    Won,
+    Lost,
 }
 
 #[derive(Clone, Debug)]
 pub struct GameOptions {
     pub starting_deck: GameStartingDeck,
     pub stake: GameStake,
+    pub seed: GameSeed,
 }
 
 pub type PlayerMoney = i32;
 
-enum BoosterPack {
-    Standard,
-    StandardJumbo,
-    StandardMega,
-
-    Arcana,
-    JumboArcana,
-    MegaArcana,
-
-    Celestial,
-    JumboCelestial,
-    MegaCelestial,
-
-    Buffoon,
-    JumboBuffoon,
-    MegaBuffoon,
-
-    Spectral,
-    JumboSpectral,
-    MegaSpectral,
-}
-
-impl BoosterPack {
-    pub fn get_cost(&self) -> u8 {
-        match self {
-            BoosterPack::Standard => 4,
-            BoosterPack::StandardJumbo => 6,
-            BoosterPack::StandardMega => 8,
-
-            BoosterPack::Arcana => 4,
-            BoosterPack::JumboArcana => 6,
-            BoosterPack::MegaArcana => 8,
-
-            BoosterPack::Celestial => 4,
-            BoosterPack::JumboCelestial => 6,
-            BoosterPack::MegaCelestial => 8,
-
-            BoosterPack::Buffoon => 4,
-            BoosterPack::JumboBuffoon => 6,
-            BoosterPack::MegaBuffoon => 8,
-
-            BoosterPack::Spectral => 4,
-            BoosterPack::JumboSpectral => 6,
-            BoosterPack::MegaSpectral => 8,
-        }
-    }
-
-    pub fn get_weight(&self) -> f32 {
-        match self {
-            BoosterPack::Standard => 4.0,
-            BoosterPack::StandardJumbo => 2.0,
-            BoosterPack::StandardMega => 0.5,
-
-            BoosterPack::Arcana => 4.0,
-            BoosterPack::JumboArcana => 2.0,
-            BoosterPack::MegaArcana => 0.5,
-
-            BoosterPack::Celestial => 4.0,
-            BoosterPack::JumboCelestial => 2.0,
-            BoosterPack::MegaCelestial => 0.5,
-
-            BoosterPack::Buffoon => 1.2,
-            BoosterPack::JumboBuffoon => 0.6,
-            BoosterPack::MegaBuffoon => 0.15,
-
-            BoosterPack::Spectral => 0.6,
-            BoosterPack::JumboSpectral => 0.3,
-            BoosterPack::MegaSpectral => 0.07,
-        }
-    }
-}
-
-enum SaleCard {
-    Joker(JokerCard),
-    Consumable(Consumable),
-}
-
-pub struct GameShopState {
-    pub cards: Vec<SaleCard>,
-
-    pub vouchers: Vec<Voucher>,
-}
-
 #[derive(Clone, Debug)]
 pub struct GameState {
     // Options
     pub stake: GameStake,
     pub starting_deck: GameStartingDeck,
+    pub rng: GameRng,
 
     pub hands_total: u8,
     pub hand_size: u8,
+    // Most cards selected at once, to play or discard
+    pub selection_limit: u8,
     pub discards_total: u8,
     pub interest_cap: u8,
     pub joker_slots: u8,
     pub consumable_slots: u8,
-    pub hand_levels: HashMap<HandType, u8>,
+    // Keyed by `HandType::get_level_type`
+    pub hand_stats: HashMap<HandType, HandStats>,
+    // For Blue seals
+    pub last_hand_played: Option<HandType>,
 
     pub vouchers: Vec<Voucher>,
     pub jokers: Vec<JokerCard>,
-    pub remaining_deck: Vec<Card>,
-    pub used_cards: Vec<Card>,
-    pub consumables: Vec<Consumable>,
+    pub deck: Deck,
+    pub consumables: Vec<ConsumableCard>,
+    // Last tarot or planet used, for The Fool
+    pub last_consumable: Option<Consumable>,
 
-    pub money: u8,
+    pub money: PlayerMoney,
     pub ante: u8,
     pub blind: Blind,
     pub boss_blind: BossBlind,
+    pub outcome: Option<GameOutcome>,
 
     pub phase: GamePhase,
+    pub cash_out: Option<CashOut>,
+
+    // Shop
+    pub shop: GameShopState,
+    pub open_pack: Option<OpenPack>,
 
     // Playing
     pub hands: u8,
     pub discards: u8,
     pub in_hand: Vec<Card>,
     pub selected_cards: Vec<Card>,
+    // Left to right order of the hand, selected cards included, so a card
+    // keeps its place while it is selected
+    pub hand_order: Vec<CardId>,
+    // Cards played since the ante started, for The Pillar
+    pub played_this_ante: HashSet<CardId>,
 
     // Scoring
-    pub chips: u32,
-    pub mult: u32,
-    pub score: u32,
+    pub score: Score,
 
     // Statistics
     pub total_tarot_played: u32,
@@ -214,22 +190,25 @@ pub struct GameState {
 impl GameState {
     pub fn new(options: GameOptions) -> Self {
         let mut deck = Vec::new();
+        let mut card_ids = CardIdAllocator::new();
 
         let mut hands = 4;
         let mut discards = 3;
         let mut money = 4;
         let mut hand_size = 8;
-        let mut interest_cap = 10;
+        // $5 of interest at most, raised to $10 and $20 by Seed Money and Money Tree
+        let mut interest_cap = 5;
         let mut consumable_slots = 2;
         let mut joker_slots = 5;
         let mut vouchers: Vec<Voucher> = vec![];
-        let mut consumables: Vec<Consumable> = vec![];
+        let mut consumables: Vec<ConsumableCard> = vec![];
+        let mut rng = GameRng::new(options.seed);
         let ante = 1;
-        let mut boss_blind = BossBlind::get_rand(ante);
+        let boss_blind = BossBlind::get_rand(ante, rng.stream(RngStream::Boss));
 
         for suit in CARD_SUITS.iter() {
             for rank in CARD_RANKS.iter() {
-                deck.push(Card::new(rank.clone(), suit.clone()));
+                deck.push(card_ids.new_card(rank.clone(), suit.clone()));
             }
         }
 
@@ -252,8 +231,8 @@ impl GameState {
             }
             GameStartingDeck::Magic => {
                 vouchers.push(Voucher::CrystalBall);
-                consumables.push(Consumable::Tarot(TarotCard::TheFool));
-                consumables.push(Consumable::Tarot(TarotCard::TheFool));
+                consumables.push(ConsumableCard::new(Consumable::Tarot(TarotCard::TheFool)));
+                consumables.push(ConsumableCard::new(Consumable::Tarot(TarotCard::TheFool)));
             }
             GameStartingDeck::Nebula => {
                 vouchers.push(Voucher::Telescope);
@@ -288,9 +267,14 @@ impl GameState {
 
             GameStartingDeck::Erratic => {
                 deck.clear();
+                card_ids = CardIdAllocator::new();
 
+                let erratic_rng = rng.stream(RngStream::Erratic);
                 for _ in 0..52 {
-                    let card = Card::new(CardRank::get_rand(), CardSuit::get_rand());
+                    let card = card_ids.new_card(
+                        CardRank::get_rand(erratic_rng),
+                        CardSuit::get_rand(erratic_rng),
+                    );
                     deck.push(card);
                 }
             }
@@ -305,39 +289,97 @@ impl GameState {
             discards,
             money,
             hand_size,
-            hand_levels: HashMap::new(),
+            selection_limit: MAX_PLAYED_CARDS as u8,
+            hand_stats: HAND_TYPES
+                .iter()
+                .filter(|hand_type| hand_type.get_level_type() == **hand_type)
+                .map(|hand_type| (hand_type.clone(), HandStats::default()))
+                .collect(),
+            last_hand_played: None,
             interest_cap,
             consumable_slots,
             joker_slots,
-            vouchers,
+            vouchers: vec![],
             consumables,
+            last_consumable: None,
 
             jokers: vec![],
             stake: options.stake,
             starting_deck: options.starting_deck,
-            remaining_deck: deck,
-            used_cards: vec![],
+            rng,
+            deck: Deck::new(deck, card_ids),
             in_hand: vec![],
             selected_cards: vec![],
+            hand_order: vec![],
+            played_this_ante: HashSet::new(),
+
+            phase: GamePhase::BlindSelect,
+            cash_out: None,
 
-            phase: GamePhase::Playing,
+            shop: GameShopState::default(),
+            open_pack: None,
 
-            ante: ante,
+            ante,
             blind: Blind::Small,
-            boss_blind: boss_blind,
+            boss_blind,
+            outcome: None,
 
-            chips: 0,
-            mult: 0,
-            score: 0,
+            score: Score::ZERO,
 
             total_tarot_played: 0,
         };
 
-        state.init_blind();
-        return state;
+        for voucher in vouchers {
+            state.add_voucher(voucher);
+        }
+
+        // The ante's voucher stays in the shop until it is bought or the ante ends
+        state.shop.vouchers = state.roll_voucher().into_iter().collect();
+
+        state.deck.shuffle(state.rng.stream(RngStream::Shuffle));
+        state
+    }
+
+    /// Creates a new playing card with an ID from this game's allocator.
+    /// Anything that adds cards mid-run (Standard packs, Cryptid, DNA, ...)
+    /// should go through here or `copy_card`, then hand the card to the deck.
+    pub fn create_card(&mut self, rank: CardRank, suit: CardSuit) -> Card {
+        self.deck.new_card(rank, suit)
+    }
+
+    pub fn copy_card(&mut self, card: &Card) -> Card {
+        self.deck.copy_card(card)
+    }
+
+    // Puts a card created mid-round straight into the hand
+    pub(crate) fn add_card_to_hand(&mut self, card: Card) {
+        self.deck.register(&card);
+        self.in_hand.push(card);
+    }
+
+    pub(crate) fn destroy_card(&mut self, card: Card) {
+        if self.is_face_card(&card) {
+            for joker in self.jokers.iter_mut() {
+                if let JokerType::Canio(destroyed) = &mut joker.joker {
+                    *destroyed = destroyed.saturating_add(1);
+                }
+            }
+        }
+
+        self.deck.destroy(card);
+    }
+
+    // Every card must be in exactly one place; a failure here is a bug in the
+    // engine. Checked after every move in every build, as the id sets it
+    // builds are linear in the size of the deck
+    fn check_cards(&self) {
+        let held = self.in_hand.iter().chain(self.selected_cards.iter());
+        if let Err(err) = self.deck.verify(held) {
+            panic!("card accounting broken: {}", err);
+        }
     }
 
-    pub fn get_score_needed(&self) -> u32 {
+    pub fn get_score_needed(&self) -> Score {
         let base_chips = get_base_score(self.ante, &self.stake);
 
         self.blind.get_min_score(base_chips)
@@ -364,172 +406,493 @@ impl GameState {
 
         println!("Selected cards: {}", debug_cards(&self.selected_cards));
         println!("In hand: {}", debug_cards(&self.in_hand));
-        println!("Used cards: {}", debug_cards(&self.used_cards));
+        println!("Discarded: {}", debug_cards(self.deck.discard_pile()));
+        println!("Draw pile: {} cards", self.deck.draw_pile().len());
 
         println!("===============================================\n");
     }
 
-    fn init_blind(&mut self) {
-        self.hands = self.hands_total;
+    pub(crate) fn init_blind(&mut self) {
+        self.hands = self.get_hands_per_round();
         self.discards = self.discards_total;
-        self.score = 0;
-        self.chips = 0;
-        self.mult = 0;
-
-        // Put all cards back into deck
-        for card in self.used_cards.iter() {
-            self.remaining_deck.push(card.clone());
-        }
-        self.used_cards.clear();
-
-        for card in self.in_hand.iter() {
-            self.remaining_deck.push(card.clone());
-        }
-        self.in_hand.clear();
+        self.score = Score::ZERO;
+        self.reset_round_hand_stats();
 
-        for card in self.selected_cards.iter() {
-            self.remaining_deck.push(card.clone());
-        }
-        self.selected_cards.clear();
+        self.fill_in_hand(DrawReason::RoundStart);
+    }
 
-        for _ in 0..self.hand_size {
-            // Get random card from remaining deck
-            let card = self.remaining_deck.choose(&mut rand::thread_rng()).unwrap();
+    // Puts every card back into the draw pile and shuffles it
+    pub(crate) fn end_round(&mut self) {
+        let mut cards = std::mem::take(&mut self.in_hand);
+        cards.append(&mut self.selected_cards);
 
-            let card_idx = self
-                .remaining_deck
-                .iter()
-                .position(|c| c.id == card.id)
-                .unwrap();
-
-            let card = self.remaining_deck.remove(card_idx);
-            self.in_hand.push(card);
-        }
+        self.deck
+            .reshuffle(cards, self.rng.stream(RngStream::Shuffle));
+        self.check_cards();
     }
 
-    pub fn start_blind(&mut self) {
-        assert_eq!(self.phase, GamePhase::Shop);
-        self.phase = GamePhase::Playing;
+    // For actions that are allowed in any phase until the run is lost
+    pub(crate) fn expect_running(&self) -> Result<(), GameError> {
+        if self.phase == GamePhase::GameOver {
+            return Err(GameError::GameOver);
+        }
 
-        self.init_blind();
+        Ok(())
     }
 
-    fn advance_blind(&mut self) {
-        assert_eq!(self.phase, GamePhase::Playing);
-
-        self.blind = match self.blind {
-            Blind::Small => Blind::Big,
-            Blind::Big => Blind::Boss(self.boss_blind.clone()),
-            Blind::Boss(_) => {
-                self.ante += 1;
-                self.boss_blind = BossBlind::get_rand(self.ante);
-                Blind::Boss(self.boss_blind.clone())
-            }
-        };
+    pub(crate) fn expect_phase(&self, phase: GamePhase) -> Result<(), GameError> {
+        if self.phase != phase {
+            return Err(GameError::WrongPhase {
+                expected: phase,
+                actual: self.phase.clone(),
+            });
+        }
 
-        self.phase = GamePhase::Shop;
+        Ok(())
     }
 
-    pub fn select_card(&mut self, card_id: u32) {
+    pub fn select_card(&mut self, card_id: CardId) -> Result<(), GameError> {
+        self.expect_phase(GamePhase::Playing)?;
+
         let card_idx = self
             .in_hand
             .iter()
             .position(|card| card.id == card_id)
-            .unwrap();
+            .ok_or(GameError::CardNotInHand(card_id))?;
+
+        let limit = self.get_selection_limit();
+        if self.selected_cards.len() >= limit {
+            return Err(GameError::TooManyCardsSelected(limit));
+        }
 
+        self.update_hand_order();
         let card: Card = self.in_hand.remove(card_idx);
         self.selected_cards.push(card);
+        self.check_cards();
+        Ok(())
     }
 
-    pub fn deselect_card(&mut self, card_id: u32) {
+    pub fn deselect_card(&mut self, card_id: CardId) -> Result<(), GameError> {
+        self.expect_phase(GamePhase::Playing)?;
+
         let card_idx = self
             .selected_cards
             .iter()
             .position(|card| card.id == card_id)
-            .unwrap();
+            .ok_or(GameError::CardNotSelected(card_id))?;
 
         let card: Card = self.selected_cards.remove(card_idx);
 
         self.in_hand.push(card);
+        self.sort_hand();
+        self.check_cards();
+        Ok(())
     }
 
-    pub fn play_hand(&mut self) -> Option<HandResult> {
-        if self.hands == 0 {
-            return Some(HandResult::Lose);
+    // Where a card sits in the hand from the left, whether selected or not
+    pub fn get_hand_position(&self, card_id: CardId) -> Option<usize> {
+        self.hand_order.iter().position(|id| *id == card_id)
+    }
+
+    // Forgets cards that left the hand and puts new ones on the right
+    pub(crate) fn update_hand_order(&mut self) {
+        let held = self
+            .in_hand
+            .iter()
+            .chain(self.selected_cards.iter())
+            .map(|card| card.id)
+            .collect::<HashSet<_>>();
+        self.hand_order.retain(|id| held.contains(id));
+
+        for card in self.in_hand.iter().chain(self.selected_cards.iter()) {
+            if !self.hand_order.contains(&card.id) {
+                self.hand_order.push(card.id);
+            }
         }
+    }
 
-        let cards = self.selected_cards.iter().collect();
+    // Puts cards returned from the selection back in their places
+    pub(crate) fn sort_hand(&mut self) {
+        self.update_hand_order();
 
-        let (hand_type, cards) = HandType::from_cards(cards);
+        let order = &self.hand_order;
+        self.in_hand
+            .sort_by_key(|card| order.iter().position(|id| *id == card.id));
+    }
 
-        let chips_and_mult = hand_type.get_value(&self.hand_levels);
+    pub fn play_hand(&mut self) -> Result<PlayedHand, GameError> {
+        self.expect_phase(GamePhase::Playing)?;
 
-        self.chips += chips_and_mult.0;
-        self.mult += chips_and_mult.1;
+        if self.hands == 0 {
+            return Err(GameError::NoHandsLeft);
+        }
 
-        // Score individual cards
-        for card in cards.iter() {
-            let mut mult = 0;
-            let mut chips = card.rank.get_base_chips();
-            chips += card.extra_chips;
+        self.expect_selection_limit()?;
 
-            match card.enhancement {
-                Some(CardEnhancement::Bonus) => chips += 30,
-                Some(CardEnhancement::Mult) => mult += 4,
-                _ => {}
-            };
+        let played = self.selected_cards.clone();
+        let rules = self.get_hand_rules();
+        let (hand_type, played) = HandType::from_cards(played.iter().collect(), &rules)?;
 
-            self.chips += chips;
-            self.mult += mult;
+        self.record_hand_played(&hand_type);
+        self.played_this_ante
+            .extend(self.selected_cards.iter().map(|card| card.id));
+        let is_allowed = self.is_hand_allowed(&played);
+        if is_allowed {
+            self.update_jokers_before_scoring(&hand_type, &played);
         }
 
-        self.hands -= 1;
-        self.score += self.chips * self.mult;
+        // Scoring reads the whole state, so it gets its own copy of the stream
+        let mut rng = self.rng.stream(RngStream::Probability).clone();
+        let trace = self.score_hand(&hand_type, &played, &mut Chance::Roll(&mut rng));
+        *self.rng.stream(RngStream::Probability) = rng;
 
-        self.chips = 0;
-        self.mult = 0;
+        self.hands -= 1;
+        self.score += trace.get_score();
+        self.money += trace.get_money() as PlayerMoney;
 
-        if self.score >= self.get_score_needed() {
-            self.advance_blind();
-            return Some(HandResult::Win);
+        if is_allowed {
+            self.update_jokers_after_scoring(&played);
         }
 
-        if self.hands == 0 && self.score < self.get_score_needed() {
-            return Some(HandResult::Lose);
+        for id in trace.get_destroyed() {
+            if let Some(idx) = self.selected_cards.iter().position(|card| card.id == id) {
+                let card = self.selected_cards.remove(idx);
+                self.destroy_card(card);
+            }
         }
 
         // Put selected cards into used cards
         self.use_selected_cards();
 
+        if self.score >= self.get_score_needed() {
+            let is_finisher = matches!(self.blind, Blind::Boss(_)) && self.ante == WINNING_ANTE;
+            if is_finisher && self.outcome.is_none() {
+                self.outcome = Some(GameOutcome::Won);
+            }
+
+            self.win_round();
+            return Ok(PlayedHand {
+                trace,
+                result: Some(HandResult::Win),
+            });
+        }
+
+        if self.hands == 0 {
+            self.outcome.get_or_insert(GameOutcome::Lost);
+            self.lose_round();
+            return Ok(PlayedHand {
+                trace,
+                result: Some(HandResult::Lose),
+            });
+        }
+
         // Redraw cards up to hand size
-        self.fill_in_hand();
-        return None;
+        self.fill_in_hand(DrawReason::HandPlayed);
+        Ok(PlayedHand {
+            trace,
+            result: None,
+        })
     }
 
     fn use_selected_cards(&mut self) {
-        for card in self.selected_cards.iter() {
-            self.used_cards.push(card.clone());
+        for card in self.selected_cards.drain(..) {
+            self.deck.discard(card);
+        }
+    }
+
+    // `selected_cards` is public, so it can be filled without `select_card`
+    fn expect_selection_limit(&self) -> Result<(), GameError> {
+        let limit = self.get_selection_limit();
+        if self.selected_cards.len() > limit {
+            return Err(GameError::TooManyCardsSelected(limit));
         }
-        self.selected_cards.clear();
+
+        Ok(())
     }
 
-    fn fill_in_hand(&mut self) {
-        let num_to_draw = self.hand_size as usize - self.in_hand.len();
+    fn fill_in_hand(&mut self, reason: DrawReason) {
+        let held = self.in_hand.len() + self.selected_cards.len();
+        let num_to_draw = (self.get_hand_size() as usize).saturating_sub(held);
         for _ in 0..num_to_draw {
-            let card = self.remaining_deck.choose(&mut rand::thread_rng()).unwrap();
-            self.in_hand.push(card.clone());
+            match self.deck.draw() {
+                Some(mut card) => {
+                    card.is_face_down = self.is_drawn_face_down(&card, reason);
+                    self.in_hand.push(card);
+                }
+                None => break,
+            }
         }
+
+        self.update_debuffs();
+        self.check_cards();
     }
 
-    pub fn discard_hand(&mut self) {
-        assert_ne!(self.discards, 0);
+    pub fn discard_hand(&mut self) -> Result<(), GameError> {
+        self.expect_phase(GamePhase::Playing)?;
+
+        if self.discards == 0 {
+            return Err(GameError::NoDiscardsLeft);
+        }
+
+        if self.selected_cards.is_empty() {
+            return Err(GameError::NoCardsSelected);
+        }
+
+        self.expect_selection_limit()?;
 
         self.discards -= 1;
-        self.selected_cards.iter().for_each(|card| {
-            self.used_cards.push(card.clone());
-        });
+        self.update_jokers_on_discard(self.selected_cards.len());
+        self.trigger_purple_seals();
+        self.use_selected_cards();
+        self.fill_in_hand(DrawReason::Discarded);
+        Ok(())
+    }
+}
+
+// The game every module's tests start from
+#[cfg(test)]
+pub(crate) fn new_test_game(
+    starting_deck: GameStartingDeck,
+    stake: GameStake,
+    seed: u64,
+) -> GameState {
+    GameState::new(GameOptions {
+        starting_deck,
+        stake,
+        seed: seed.into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{card::CardEdition, joker::JokerType};
+
+    // Starts a game and plays its first blind
+    fn new_game(seed: u64) -> GameState {
+        let mut state = new_test_game(GameStartingDeck::Erratic, GameStake::White, seed);
+        state.select_blind().unwrap();
+        state
+    }
+
+    fn card_ids(cards: &[Card]) -> Vec<(CardRank, CardSuit)> {
+        cards
+            .iter()
+            .map(|card| (card.rank.clone(), card.suit.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_same_seed_same_run() {
+        let a = new_game(1234);
+        let b = new_game(1234);
+
+        assert_eq!(a.boss_blind, b.boss_blind);
+        assert_eq!(card_ids(&a.in_hand), card_ids(&b.in_hand));
+        assert_eq!(card_ids(a.deck.draw_pile()), card_ids(b.deck.draw_pile()));
+    }
+
+    #[test]
+    fn test_card_ids_start_fresh_each_game() {
+        let _ = new_game(1);
+        let state = new_game(2);
+
+        let mut ids = state
+            .in_hand
+            .iter()
+            .chain(state.deck.draw_pile().iter())
+            .map(|card| card.id)
+            .collect::<Vec<_>>();
+        ids.sort();
+
+        assert_eq!(ids, (0..52).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_illegal_actions_return_errors() {
+        let mut state = new_game(7);
+
+        assert_eq!(state.select_card(999), Err(GameError::CardNotInHand(999)));
+        assert_eq!(
+            state.deselect_card(999),
+            Err(GameError::CardNotSelected(999))
+        );
+        assert_eq!(state.play_hand(), Err(GameError::NoCardsSelected));
+        assert_eq!(state.discard_hand(), Err(GameError::NoCardsSelected));
+        assert_eq!(
+            state.select_blind(),
+            Err(GameError::WrongPhase {
+                expected: GamePhase::BlindSelect,
+                actual: GamePhase::Playing,
+            })
+        );
+
+        let id = state.in_hand[0].id;
+        state.discards = 0;
+        state.select_card(id).unwrap();
+        assert_eq!(state.discard_hand(), Err(GameError::NoDiscardsLeft));
+
+        state.hands = 0;
+        assert_eq!(state.play_hand(), Err(GameError::NoHandsLeft));
+    }
+
+    #[test]
+    fn test_discard_draws_from_draw_pile() {
+        let mut state = new_game(5);
+        assert_eq!(state.deck.draw_pile().len(), 52 - 8);
+
+        let ids = state.in_hand[..3]
+            .iter()
+            .map(|card| card.id)
+            .collect::<Vec<_>>();
+        for id in ids.iter() {
+            state.select_card(*id).unwrap();
+        }
+        state.discard_hand().unwrap();
+
+        assert_eq!(state.in_hand.len(), 8);
+        assert_eq!(state.deck.discard_pile().len(), 3);
+        assert_eq!(state.deck.draw_pile().len(), 52 - 8 - 3);
+        assert!(state.in_hand.iter().all(|card| !ids.contains(&card.id)));
+    }
+
+    #[test]
+    fn test_beating_finisher_wins_into_endless() {
+        let mut state = new_game(8);
+        state.ante = WINNING_ANTE;
+        state.blind = Blind::Boss(BossBlind::VioletVessel);
+
+        for expected in [Some(GameOutcome::Won), Some(GameOutcome::Won)] {
+            state.score = state.get_score_needed();
+            let id = state.in_hand[0].id;
+            state.select_card(id).unwrap();
+            assert_eq!(
+                state.play_hand().map(|played| played.result),
+                Ok(Some(HandResult::Win))
+            );
+            assert_eq!(state.outcome, expected);
+
+            state.collect_cash_out().unwrap();
+            state.leave_shop().unwrap();
+            state.select_blind().unwrap();
+        }
+
+        assert!(state.ante > WINNING_ANTE);
+        assert!(state.get_score_needed() > Score::ZERO);
+    }
+
+    #[test]
+    fn test_secret_hands_become_visible() {
+        let mut state = new_game(3);
+        assert!(!state.is_hand_visible(&HandType::FlushFive));
+        assert_eq!(state.get_visible_hands().len(), 10);
+
+        for card in state.in_hand.iter_mut().take(5) {
+            card.rank = CardRank::Queen;
+            card.suit = CardSuit::Hearts;
+        }
+        for id in state.in_hand[..5]
+            .iter()
+            .map(|card| card.id)
+            .collect::<Vec<_>>()
+        {
+            state.select_card(id).unwrap();
+        }
+        state.play_hand().unwrap();
+
+        assert!(state.is_hand_visible(&HandType::FlushFive));
+        assert!(!state.is_hand_visible(&HandType::FlushHouse));
+        assert_eq!(state.get_visible_hands().len(), 11);
+    }
+
+    #[test]
+    fn test_hand_stats() {
+        let mut state = new_game(4);
+        assert_eq!(state.get_hand_level(&HandType::Pair), 1);
+        assert_eq!(state.get_most_played_hand(), None);
+
+        for _ in 0..2 {
+            let id = state.in_hand[0].id;
+            state.select_card(id).unwrap();
+            state.play_hand().unwrap();
+        }
+
+        let high_card = state.get_hand_stats(&HandType::HighCard).clone();
+        assert_eq!(high_card.played, 2);
+        assert_eq!(high_card.played_this_round, 2);
+        assert_eq!(state.get_most_played_hand(), Some(HandType::HighCard));
+
+        // Royal Flush shares the Straight Flush record
+        state.record_hand_played(&HandType::RoyalFlush);
+        state.record_hand_played(&HandType::RoyalFlush);
+        assert_eq!(state.get_hand_stats(&HandType::StraightFlush).played, 2);
+        assert_eq!(state.get_most_played_hand(), Some(HandType::StraightFlush));
+
+        state.level_up_hand(HandType::RoyalFlush);
+        assert_eq!(state.get_hand_level(&HandType::StraightFlush), 2);
+
+        state.init_blind();
+        assert_eq!(state.get_hand_stats(&HandType::HighCard).played, 2);
+        assert_eq!(
+            state.get_hand_stats(&HandType::HighCard).played_this_round,
+            0
+        );
+    }
+
+    #[test]
+    fn test_negative_slots() {
+        let mut state = new_game(0);
+        state.jokers.clear();
+        state.joker_slots = 1;
+
+        state.add_joker(JokerCard::new(JokerType::Joker)).unwrap();
+        assert!(state
+            .add_joker(JokerCard::new(JokerType::Abstract))
+            .is_err());
+
+        // A Negative joker fits even when full, and adds its own slot
+        let mut negative = JokerCard::new(JokerType::Abstract);
+        negative.edition = CardEdition::Negative;
+        state.add_joker(negative).unwrap();
+        assert_eq!(state.get_joker_slots(), 2);
+        assert!(state.add_joker(JokerCard::new(JokerType::Blue)).is_err());
+
+        state.consumables.clear();
+        state.consumable_slots = 1;
+        let mut negative = ConsumableCard::new(Consumable::Tarot(TarotCard::TheFool));
+        negative.edition = CardEdition::Negative;
+        state.add_consumable_card(negative).unwrap();
+        state
+            .add_consumable(Consumable::Tarot(TarotCard::TheFool))
+            .unwrap();
+        assert_eq!(state.consumables.len(), 2);
+        assert!(state
+            .add_consumable(Consumable::Tarot(TarotCard::TheFool))
+            .is_err());
+    }
+
+    #[test]
+    fn test_cloned_state_branches_deterministically() {
+        let mut a = new_game(99);
+        let mut b = a.clone();
+
+        for state in [&mut a, &mut b] {
+            let id = state.in_hand[0].id;
+            state.select_card(id).unwrap();
+            state.discard_hand().unwrap();
+        }
+
+        assert_eq!(card_ids(&a.in_hand), card_ids(&b.in_hand));
+    }
 
-        self.selected_cards.clear();
-        self.fill_in_hand();
+    // Runs in release builds too, where the long simulations are
+    #[test]
+    #[should_panic(expected = "card accounting broken")]
+    fn test_duplicated_card_is_caught() {
+        let mut state = new_game(1);
+        let copy = state.in_hand[0].clone();
+        state.in_hand.push(copy);
+        let id = state.in_hand[1].id;
+        state.select_card(id).unwrap();
     }
 }
@@ -1,17 +1,37 @@
 use card_game_ai::{
-    hand::HandType, stake::GameStake, GameOptions, GamePhase, GameStartingDeck, GameState,
-    HandResult,
+    action::GameAction, card::Card, error::GameError, hand::HandType, rng::GameSeed,
+    stake::GameStake, GameOptions, GameOutcome, GamePhase, GameStartingDeck, GameState, HandResult,
 };
 use rand::prelude::IteratorRandom;
-use rand::{seq::SliceRandom, Rng};
+use rand::{rngs::StdRng, SeedableRng};
+
+fn main() -> Result<(), GameError> {
+    // Pass a seed as the first argument to replay a run
+    let seed = match std::env::args().nth(1) {
+        Some(arg) => match arg.parse::<GameSeed>() {
+            Ok(seed) => seed,
+            Err(err) => {
+                eprintln!("Invalid seed {:?}: {:?}", arg, err);
+                std::process::exit(1);
+            }
+        },
+        None => GameSeed::random(&mut rand::thread_rng()),
+    };
+    println!("Seed: {}", seed);
+
+    // The AI's own choices are seeded too so a whole run can be reproduced
+    let mut ai_rng = StdRng::seed_from_u64(seed.value());
 
-fn main() {
     let mut state = GameState::new(GameOptions {
         stake: GameStake::White,
         starting_deck: GameStartingDeck::Red,
+        seed,
     });
 
+    let mut announced_win = false;
     loop {
+        state.apply(GameAction::SelectBlind)?;
+
         println!("Ante: {}", state.ante);
         println!("Chips needed: {}", state.get_score_needed());
         println!("Blind: {:?}", state.blind);
@@ -21,60 +41,80 @@ fn main() {
         loop {
             state.print_state();
 
-            let mut cards_to_play = state.in_hand.clone();
-
-            let possible_hands = HandType::get_possible_hands(cards_to_play.iter().collect());
-
-            // Get best hand by chips * mult
-            let mut best_hand = possible_hands.iter().max_by(|a, b| {
-                let (chips_a, mult_a) = a.0.get_value(&state.hand_levels);
-                let (chips_b, mult_b) = b.0.get_value(&state.hand_levels);
-                (chips_a * mult_a).cmp(&(chips_b * mult_b))
-            });
-
-            let best_hand = best_hand.unwrap();
-            let (chips, mult) = best_hand.0.get_value(&state.hand_levels);
-
-            let best_score = chips * mult;
-            if best_score < state.get_score_needed() - state.score {
-                println!("Discarding half the cards");
-
-                // Discard half the cards
-                let half_cards = state.in_hand.len() / 2;
-
-                // select them
-                let cards_to_discard = state
-                    .in_hand
-                    .iter()
-                    .map(|card| card.id)
-                    .choose_multiple(&mut rand::thread_rng(), half_cards);
-
-                for card in cards_to_discard {
-                    state.select_card(card);
+            // Plays are ranked best first
+            let plays = state.get_best_plays();
+            let cards = match plays.first() {
+                // Face down cards are never ranked, so with nothing else in hand
+                // play one blind
+                None => {
+                    println!("Every card is face down, playing one blind\n");
+                    vec![state.in_hand[0].id]
                 }
-                state.discard_hand();
-
-                state.print_state();
+                Some(best_play)
+                    if best_play.score < state.get_score_needed() - state.score
+                        && state.discards > 0 =>
+                {
+                    println!("Discarding half the cards");
+
+                    // Discard half the cards
+                    let half_cards = state.in_hand.len() / 2;
+
+                    // select them
+                    let cards_to_discard = state
+                        .in_hand
+                        .iter()
+                        .map(|card| card.id)
+                        .choose_multiple(&mut ai_rng, half_cards);
+
+                    for card in cards_to_discard {
+                        state.apply(GameAction::SelectCard(card))?;
+                    }
+                    state.apply(GameAction::DiscardHand)?;
+
+                    // The hand changed, so look for the best hand again
+                    continue;
+                }
+                Some(best_play) => {
+                    println!(
+                        "Best Hand: {:?} ({}): {:?}\n",
+                        best_play.hand_type, best_play.score, best_play.cards
+                    );
+                    best_play.cards.clone()
+                }
+            };
+            for id in cards {
+                state.apply(GameAction::SelectCard(id))?;
             }
 
-            println!(
-                "Best Hand: {:?} ({} x {}): {:?}\n",
-                best_hand.0, chips, mult, best_hand.1
-            );
-            for card in best_hand.1.iter() {
-                state.select_card(card.id.clone());
+            // Only what the player can see; with a face down card played, which
+            // cards score would give it away
+            let rules = state.get_hand_rules();
+            let (_, played) = HandType::from_cards(state.selected_cards.iter().collect(), &rules)?;
+            let all_visible = played.iter().all(|played| !played.card.is_face_down);
+            for played in played.iter() {
+                let note = if played.is_scoring || !all_visible {
+                    ""
+                } else {
+                    " (not scored)"
+                };
+                println!("  {}{}", describe_card(played.card), note);
             }
 
-            let result = state.play_hand();
+            let played = state.play_hand()?;
+            println!("{}", played.trace);
             println!("Score: {}", state.score);
             println!("\n\n");
 
-            if let Some(result) = result {
+            if let Some(result) = played.result {
                 if result == HandResult::Lose {
                     println!("You lost the round");
-                    return;
+                    return Ok(());
                 } else {
                     println!("You won the round");
+                    if state.outcome == Some(GameOutcome::Won) && !announced_win {
+                        println!("You won the run! Continuing in endless mode");
+                        announced_win = true;
+                    }
                     break;
                 }
             }
@@ -83,10 +123,23 @@ fn main() {
             println!("Continuing...\n");
         }
 
+        if let Some(cash_out) = &state.cash_out {
+            println!("Cash out: ${} {:?}", cash_out.get_total(), cash_out);
+        }
+        state.apply(GameAction::CashOut)?;
+
         // Do the shop
         assert_eq!(state.phase, GamePhase::Shop);
 
         println!("Starting blind");
-        state.start_blind();
+        state.apply(GameAction::LeaveShop)?;
+    }
+}
+
+// A card as the player sees it, using only its visible rank and suit
+fn describe_card(card: &Card) -> String {
+    match (card.get_visible_rank(), card.get_visible_suit()) {
+        (Some(rank), Some(suit)) => format!("[{:?} {:?}]", rank, suit),
+        _ => "[?]".to_string(),
     }
 }
@@ -0,0 +1,245 @@
+use std::{fmt, str::FromStr};
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+// Characters the game uses for seeds; 0 and O are left out so they can't be confused.
+pub const SEED_CHARS: &str = "123456789ABCDEFGHIJKLMNPQRSTUVWXYZ";
+pub const SEED_LEN: usize = 8;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GameSeed {
+    Numeric(u64),
+    // Alphanumeric seed as shown in game, e.g. "7LB2WVPK"
+    Text(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SeedError {
+    Empty,
+    TooLong,
+    InvalidChar(char),
+    // All digits, but too big for a numeric seed
+    NumberTooLarge,
+}
+
+impl GameSeed {
+    pub fn random(rng: &mut impl Rng) -> Self {
+        let chars = SEED_CHARS.chars().collect::<Vec<_>>();
+        loop {
+            let text = (0..SEED_LEN)
+                .map(|_| *chars.choose(rng).unwrap())
+                .collect::<String>();
+
+            // All digits would read back as a numeric seed
+            if !text.chars().all(|c| c.is_ascii_digit()) {
+                return GameSeed::Text(text);
+            }
+        }
+    }
+
+    pub fn value(&self) -> u64 {
+        match self {
+            GameSeed::Numeric(value) => *value,
+            GameSeed::Text(text) => fnv1a(FNV_OFFSET, text.as_bytes()),
+        }
+    }
+}
+
+impl From<u64> for GameSeed {
+    fn from(value: u64) -> Self {
+        GameSeed::Numeric(value)
+    }
+}
+
+impl FromStr for GameSeed {
+    type Err = SeedError;
+
+    // All digits is a numeric seed, as `Display` prints one
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text = s.trim().to_ascii_uppercase();
+
+        if text.is_empty() {
+            return Err(SeedError::Empty);
+        }
+
+        if text.chars().all(|c| c.is_ascii_digit()) {
+            return text
+                .parse()
+                .map(GameSeed::Numeric)
+                .map_err(|_| SeedError::NumberTooLarge);
+        }
+
+        if text.chars().count() > SEED_LEN {
+            return Err(SeedError::TooLong);
+        }
+
+        if let Some(c) = text.chars().find(|c| !SEED_CHARS.contains(*c)) {
+            return Err(SeedError::InvalidChar(c));
+        }
+
+        Ok(GameSeed::Text(text))
+    }
+}
+
+impl fmt::Display for GameSeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameSeed::Numeric(value) => write!(f, "{}", value),
+            GameSeed::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+/// Independent source of randomness. Each stream is seeded from the game seed
+/// and its own name, so drawing from one never shifts the results of another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RngStream {
+    Shuffle,
+    Boss,
+    Shop,
+    Pack,
+    Probability,
+    Erratic,
+    Consumable,
+}
+
+pub const RNG_STREAMS: [RngStream; 7] = [
+    RngStream::Shuffle,
+    RngStream::Boss,
+    RngStream::Shop,
+    RngStream::Pack,
+    RngStream::Probability,
+    RngStream::Erratic,
+    RngStream::Consumable,
+];
+
+impl RngStream {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            RngStream::Shuffle => "shuffle",
+            RngStream::Boss => "boss",
+            RngStream::Shop => "shop",
+            RngStream::Pack => "pack",
+            RngStream::Probability => "probability",
+            RngStream::Erratic => "erratic",
+            RngStream::Consumable => "consumable",
+        }
+    }
+
+    // Position in `RNG_STREAMS`, which is also where `GameRng` keeps the stream
+    pub const fn get_index(&self) -> usize {
+        match self {
+            RngStream::Shuffle => 0,
+            RngStream::Boss => 1,
+            RngStream::Shop => 2,
+            RngStream::Pack => 3,
+            RngStream::Probability => 4,
+            RngStream::Erratic => 5,
+            RngStream::Consumable => 6,
+        }
+    }
+}
+
+/// Seeded random number generators owned by a single game, one per `RngStream`.
+///
+/// Every random decision in a run goes through this, so the same seed and the
+/// same actions always produce the same run. Cloning a `GameState` clones the
+/// generators along with it.
+#[derive(Clone, Debug)]
+pub struct GameRng {
+    seed: GameSeed,
+    streams: Vec<StdRng>,
+}
+
+impl GameRng {
+    pub fn new(seed: GameSeed) -> Self {
+        let value = seed.value();
+        let streams = RNG_STREAMS
+            .iter()
+            .map(|stream| {
+                let stream_seed = fnv1a(value, stream.get_name().as_bytes());
+                StdRng::seed_from_u64(stream_seed)
+            })
+            .collect();
+
+        Self { seed, streams }
+    }
+
+    pub fn seed(&self) -> &GameSeed {
+        &self.seed
+    }
+
+    pub fn stream(&mut self, stream: RngStream) -> &mut StdRng {
+        &mut self.streams[stream.get_index()]
+    }
+}
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+// Stable across platforms and Rust versions, unlike `DefaultHasher`.
+fn fnv1a(init: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(init, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_seed() {
+        assert_eq!(
+            "7lb2wvpk".parse::<GameSeed>(),
+            Ok(GameSeed::Text("7LB2WVPK".to_string()))
+        );
+        assert_eq!("".parse::<GameSeed>(), Err(SeedError::Empty));
+        assert_eq!("ABCDEFGHJ".parse::<GameSeed>(), Err(SeedError::TooLong));
+        assert_eq!("ABC0".parse::<GameSeed>(), Err(SeedError::InvalidChar('0')));
+    }
+
+    #[test]
+    fn test_parse_numeric_seed() {
+        assert_eq!("1234".parse::<GameSeed>(), Ok(GameSeed::Numeric(1234)));
+        assert_eq!("100".parse::<GameSeed>(), Ok(GameSeed::Numeric(100)));
+        assert_eq!(
+            GameSeed::Numeric(u64::MAX).to_string().parse::<GameSeed>(),
+            Ok(GameSeed::Numeric(u64::MAX))
+        );
+        assert_eq!(
+            "99999999999999999999".parse::<GameSeed>(),
+            Err(SeedError::NumberTooLarge)
+        );
+    }
+
+    #[test]
+    fn test_random_seed_is_valid() {
+        let seed = GameSeed::random(&mut StdRng::seed_from_u64(0));
+        assert_eq!(seed.to_string().parse::<GameSeed>(), Ok(seed));
+    }
+
+    #[test]
+    fn test_stream_index() {
+        for (idx, stream) in RNG_STREAMS.iter().enumerate() {
+            assert_eq!(stream.get_index(), idx, "{:?}", stream);
+        }
+    }
+
+    #[test]
+    fn test_streams_are_independent() {
+        let seed: GameSeed = "TESTSEED".parse().unwrap();
+        let mut a = GameRng::new(seed.clone());
+        let mut b = GameRng::new(seed);
+
+        // Drawing from the shop stream must not shift the boss stream
+        for _ in 0..10 {
+            a.stream(RngStream::Shop).gen::<u32>();
+        }
+
+        assert_eq!(
+            a.stream(RngStream::Boss).gen::<u64>(),
+            b.stream(RngStream::Boss).gen::<u64>()
+        );
+    }
+}
@@ -0,0 +1,633 @@
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    blind::{Blind, BossBlind},
+    card::{Card, CardEdition, CardEnhancement, CardSuit},
+    consumable::ConsumableCard,
+    error::GameError,
+    hand::{PlayedCard, MAX_PLAYED_CARDS},
+    joker::JokerType,
+    rng::RngStream,
+    stake::GameStake,
+    DrawReason, GamePhase, GameStartingDeck, GameState, PlayerMoney,
+};
+
+// $1 of interest for every $5 held, up to `interest_cap`
+pub const INTEREST_STEP: PlayerMoney = 5;
+
+// For each Gold card held in hand when the blind is beaten
+pub const GOLD_CARD_PAYOUT: PlayerMoney = 3;
+
+/// The money paid out after a blind is beaten, line by line as the cash-out
+/// screen shows it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CashOut {
+    pub blind: PlayerMoney,
+    pub hands: PlayerMoney,
+    pub discards: PlayerMoney,
+    pub interest: PlayerMoney,
+    pub jokers: PlayerMoney,
+    pub gold_cards: PlayerMoney,
+}
+
+impl CashOut {
+    pub fn get_total(&self) -> PlayerMoney {
+        self.blind + self.hands + self.discards + self.interest + self.jokers + self.gold_cards
+    }
+}
+
+impl GameState {
+    /// Plays the blind on deck.
+    pub fn select_blind(&mut self) -> Result<(), GameError> {
+        self.expect_phase(GamePhase::BlindSelect)?;
+        self.phase = GamePhase::Playing;
+
+        self.init_blind();
+        Ok(())
+    }
+
+    /// Skips the Small or Big blind on deck; the boss blind can't be skipped.
+    pub fn skip_blind(&mut self) -> Result<(), GameError> {
+        self.expect_phase(GamePhase::BlindSelect)?;
+
+        self.blind = match self.blind {
+            Blind::Small => Blind::Big,
+            Blind::Big => Blind::Boss(self.boss_blind.clone()),
+            Blind::Boss(_) => return Err(GameError::CannotSkipBlind),
+        };
+
+        Ok(())
+    }
+
+    /// Works out the payout for the blind just beaten, moves on to the next
+    /// blind and waits on the cash-out screen.
+    pub(crate) fn win_round(&mut self) {
+        self.trigger_blue_seals();
+
+        self.cash_out = Some(self.get_cash_out());
+
+        self.end_round();
+
+        let beat_boss = matches!(self.blind, Blind::Boss(_));
+        self.blind = match self.blind {
+            Blind::Small => Blind::Big,
+            Blind::Big => Blind::Boss(self.boss_blind.clone()),
+            Blind::Boss(_) => {
+                self.ante = self.ante.saturating_add(1);
+                self.played_this_ante.clear();
+                self.boss_blind = BossBlind::get_rand(self.ante, self.rng.stream(RngStream::Boss));
+                Blind::Small
+            }
+        };
+
+        self.phase = GamePhase::CashOut;
+        self.restock_shop(beat_boss);
+    }
+
+    // The boss blind being played, if any. Chicot turns its effect off
+    pub fn get_active_boss(&self) -> Option<&BossBlind> {
+        if self.has_joker(&JokerType::Chicot) {
+            return None;
+        }
+
+        match (&self.phase, &self.blind) {
+            (GamePhase::Playing, Blind::Boss(boss)) => Some(boss),
+            _ => None,
+        }
+    }
+
+    // Cards drawn up to, counting selected cards
+    pub fn get_hand_size(&self) -> u8 {
+        let mut hand_size = self.hand_size;
+        for joker in self.jokers.iter() {
+            match joker.joker {
+                JokerType::Juggler => hand_size = hand_size.saturating_add(1),
+                JokerType::Troubadour => hand_size = hand_size.saturating_add(2),
+                _ => {}
+            }
+        }
+
+        if self.get_active_boss() == Some(&BossBlind::Manacle) {
+            hand_size = hand_size.saturating_sub(1);
+        }
+
+        hand_size
+    }
+
+    // Hands at the start of a blind, always at least one
+    pub fn get_hands_per_round(&self) -> u8 {
+        let troubadours = self
+            .jokers
+            .iter()
+            .filter(|joker| joker.joker == JokerType::Troubadour)
+            .count() as u8;
+
+        self.hands_total.saturating_sub(troubadours).max(1)
+    }
+
+    // Most cards that can be selected to play or discard at once
+    pub fn get_selection_limit(&self) -> usize {
+        self.selection_limit as usize
+    }
+
+    // Bosses can refuse a hand, which is still played but scores nothing
+    pub fn is_hand_allowed(&self, played: &[PlayedCard]) -> bool {
+        match self.get_active_boss() {
+            Some(BossBlind::Psychic) => played.len() == MAX_PLAYED_CARDS,
+            _ => true,
+        }
+    }
+
+    // A debuffed card still counts toward the hand, but scores nothing and
+    // triggers nothing
+    pub fn is_debuffed_by_boss(&self, card: &Card) -> bool {
+        let suit = match self.get_active_boss() {
+            Some(BossBlind::Club) => CardSuit::Clubs,
+            Some(BossBlind::Goad) => CardSuit::Spades,
+            Some(BossBlind::Window) => CardSuit::Diamonds,
+            Some(BossBlind::Head) => CardSuit::Hearts,
+            Some(BossBlind::Plant) => return self.is_face_card(card),
+            Some(BossBlind::Pillar) => return self.played_this_ante.contains(&card.id),
+            _ => return false,
+        };
+
+        self.get_hand_rules().is_suit(card, &suit)
+    }
+
+    // Cards in hand can change suit or rank mid-round, so this runs after each draw
+    pub(crate) fn update_debuffs(&mut self) {
+        let debuffed = self
+            .in_hand
+            .iter()
+            .chain(self.selected_cards.iter())
+            .map(|card| self.is_debuffed_by_boss(card))
+            .collect::<Vec<_>>();
+
+        let cards = self
+            .in_hand
+            .iter_mut()
+            .chain(self.selected_cards.iter_mut());
+        for (card, is_debuffed) in cards.zip(debuffed) {
+            card.is_debuffed = is_debuffed;
+        }
+    }
+
+    pub(crate) fn is_drawn_face_down(&mut self, card: &Card, reason: DrawReason) -> bool {
+        match self.get_active_boss() {
+            Some(BossBlind::House) => reason == DrawReason::RoundStart,
+            Some(BossBlind::Fish) => reason == DrawReason::HandPlayed,
+            Some(BossBlind::Mark) => self.is_face_card(card),
+            Some(BossBlind::Wheel) => self.rng.stream(RngStream::Probability).gen_range(0..7) == 0,
+            _ => false,
+        }
+    }
+
+    pub(crate) fn lose_round(&mut self) {
+        self.phase = GamePhase::GameOver;
+    }
+
+    fn get_cash_out(&self) -> CashOut {
+        // From Red Stake up the Small Blind pays nothing
+        let blind = match self.blind {
+            Blind::Small if self.stake != GameStake::White => 0,
+            _ => self.blind.get_reward(),
+        };
+
+        let (per_hand, per_discard) = match self.starting_deck {
+            GameStartingDeck::Green => (2, 1),
+            _ => (1, 0),
+        };
+
+        // Gold cards held pay out before interest is counted, so their money
+        // earns interest too. Retriggers pay a Gold card out again
+        let gold_cards = self
+            .in_hand
+            .iter()
+            .filter(|card| card.enhancement == Some(CardEnhancement::Gold) && !card.is_debuffed)
+            .map(|card| 1 + self.get_retriggers(card, true).len())
+            .sum::<usize>() as PlayerMoney
+            * GOLD_CARD_PAYOUT;
+
+        // The Green Deck's interest cap is 0, so it earns no interest
+        let interest =
+            ((self.money + gold_cards) / INTEREST_STEP).clamp(0, self.interest_cap as PlayerMoney);
+
+        let jokers = self
+            .jokers
+            .iter()
+            .map(|joker| match joker.joker {
+                JokerType::DelayedGratification if self.discards == self.discards_total => {
+                    2 * self.discards as PlayerMoney
+                }
+                _ => 0,
+            })
+            .sum();
+
+        CashOut {
+            blind: blind as PlayerMoney,
+            hands: per_hand * self.hands as PlayerMoney,
+            discards: per_discard * self.discards as PlayerMoney,
+            interest,
+            jokers,
+            gold_cards,
+        }
+    }
+
+    /// Collects the payout and opens the shop.
+    pub fn collect_cash_out(&mut self) -> Result<(), GameError> {
+        self.expect_phase(GamePhase::CashOut)?;
+
+        let cash_out = self.cash_out.take().unwrap_or_default();
+        self.money += cash_out.get_total();
+        self.phase = GamePhase::Shop;
+        Ok(())
+    }
+
+    pub fn leave_shop(&mut self) -> Result<(), GameError> {
+        self.expect_shop()?;
+        self.trigger_perkeo();
+        self.phase = GamePhase::BlindSelect;
+        Ok(())
+    }
+
+    // Each Perkeo makes a Negative copy of a random consumable held
+    fn trigger_perkeo(&mut self) {
+        let perkeos = self
+            .jokers
+            .iter()
+            .filter(|joker| joker.joker == JokerType::Perkeo)
+            .count();
+        for _ in 0..perkeos {
+            let Some(consumable) = self
+                .consumables
+                .choose(self.rng.stream(RngStream::Consumable))
+                .cloned()
+            else {
+                return;
+            };
+
+            let mut copy = ConsumableCard::new(consumable.consumable);
+            copy.edition = CardEdition::Negative;
+            let _ = self.add_consumable_card(copy);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        action::GameAction,
+        card::{CardIdAllocator, CardRank},
+        consumable::{Consumable, TarotCard},
+        hand::{HandRules, HandType},
+        joker::JokerCard,
+        new_test_game,
+        score::Score,
+        scoring::Chance,
+        voucher::Voucher,
+        HandResult,
+    };
+
+    fn win_blind(state: &mut GameState) {
+        state.select_blind().unwrap();
+        state.score = state.get_score_needed();
+
+        let id = state.in_hand[0].id;
+        state.select_card(id).unwrap();
+        assert_eq!(
+            state.play_hand().map(|played| played.result),
+            Ok(Some(HandResult::Win))
+        );
+    }
+
+    #[test]
+    fn test_round_lifecycle() {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 10);
+        assert_eq!(state.phase, GamePhase::BlindSelect);
+        assert!(state.in_hand.is_empty());
+
+        for (blind, ante) in [(Blind::Big, 1), (Blind::Small, 2)] {
+            win_blind(&mut state);
+            assert_eq!(state.phase, GamePhase::CashOut);
+            assert_eq!(
+                state.leave_shop(),
+                Err(GameError::WrongPhase {
+                    expected: GamePhase::Shop,
+                    actual: GamePhase::CashOut,
+                })
+            );
+
+            state.collect_cash_out().unwrap();
+            state.leave_shop().unwrap();
+            assert_eq!(state.phase, GamePhase::BlindSelect);
+            assert_eq!(state.blind, blind);
+            assert_eq!(state.ante, ante);
+
+            if blind == Blind::Big {
+                state.skip_blind().unwrap();
+                assert!(matches!(state.blind, Blind::Boss(_)));
+                assert_eq!(state.skip_blind(), Err(GameError::CannotSkipBlind));
+            }
+        }
+    }
+
+    #[test]
+    fn test_voucher_offered_from_the_first_shop() {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 10);
+        let voucher = state.shop.vouchers.clone();
+        assert_eq!(voucher.len(), 1);
+
+        // The same voucher is offered until the boss is beaten
+        win_blind(&mut state);
+        assert_eq!(state.shop.vouchers, voucher);
+        state.collect_cash_out().unwrap();
+        state.leave_shop().unwrap();
+        win_blind(&mut state);
+        assert_eq!(state.shop.vouchers, voucher);
+    }
+
+    #[test]
+    fn test_cash_out() {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 10);
+        state.money = 23;
+        win_blind(&mut state);
+
+        let cash_out = state.cash_out.clone().unwrap();
+        assert_eq!(
+            cash_out,
+            CashOut {
+                blind: 3,
+                hands: 3,
+                discards: 0,
+                interest: 4,
+                jokers: 0,
+                gold_cards: 0,
+            }
+        );
+
+        state.collect_cash_out().unwrap();
+        assert_eq!(state.money, 23 + 10);
+
+        let mut state = new_test_game(GameStartingDeck::Green, GameStake::Red, 10);
+        state.money = 23;
+        win_blind(&mut state);
+        assert_eq!(
+            state.cash_out,
+            Some(CashOut {
+                blind: 0,
+                hands: 6,
+                discards: 3,
+                interest: 0,
+                jokers: 0,
+                gold_cards: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_interest_cap() {
+        let interest = |voucher: Option<Voucher>| {
+            let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 10);
+            if let Some(voucher) = voucher {
+                state.add_voucher(voucher);
+            }
+            state.money = 200;
+            win_blind(&mut state);
+            state.cash_out.unwrap().interest
+        };
+
+        assert_eq!(interest(None), 5);
+        assert_eq!(interest(Some(Voucher::SeedMoney)), 10);
+        assert_eq!(interest(Some(Voucher::MoneyTree)), 20);
+    }
+
+    #[test]
+    fn test_selection_limit() {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 10);
+        state.select_blind().unwrap();
+
+        let ids = state.in_hand.iter().map(|card| card.id).collect::<Vec<_>>();
+        for id in ids[..5].iter() {
+            state.select_card(*id).unwrap();
+        }
+        assert_eq!(
+            state.select_card(ids[5]),
+            Err(GameError::TooManyCardsSelected(5))
+        );
+        assert!(!state
+            .legal_actions()
+            .contains(&GameAction::SelectCard(ids[5])));
+
+        // Selected cards still count towards the hand size
+        state.discard_hand().unwrap();
+        assert_eq!(state.in_hand.len(), 8);
+        state.select_card(state.in_hand[0].id).unwrap();
+        state.fill_in_hand(DrawReason::RoundStart);
+        assert_eq!(state.in_hand.len() + state.selected_cards.len(), 8);
+    }
+
+    #[test]
+    fn test_changed_selection_limit() {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 10);
+        state.selection_limit = 3;
+        state.select_blind().unwrap();
+
+        let ids = state.in_hand.iter().map(|card| card.id).collect::<Vec<_>>();
+        assert_eq!(
+            state.preview_hand(&ids[..4]),
+            Err(GameError::TooManyCardsSelected(3))
+        );
+        assert!(state
+            .get_best_plays()
+            .iter()
+            .all(|play| play.cards.len() <= 3));
+
+        for id in ids[..3].iter() {
+            state.select_card(*id).unwrap();
+        }
+        assert_eq!(
+            state.select_card(ids[3]),
+            Err(GameError::TooManyCardsSelected(3))
+        );
+        assert!(!state
+            .legal_actions()
+            .iter()
+            .any(|action| matches!(action, GameAction::SelectCard(_))));
+    }
+
+    #[test]
+    fn test_hand_size_effects() {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 10);
+        state.jokers.push(JokerCard::new(JokerType::Juggler));
+        state.jokers.push(JokerCard::new(JokerType::Troubadour));
+        state.select_blind().unwrap();
+        assert_eq!(state.in_hand.len(), 8 + 1 + 2);
+        assert_eq!(state.hands, 3);
+
+        state.jokers.clear();
+        state.blind = Blind::Boss(BossBlind::Manacle);
+        state.end_round();
+        state.init_blind();
+        assert_eq!(state.in_hand.len(), 7);
+    }
+
+    #[test]
+    fn test_psychic_refuses_small_hands() {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 10);
+        state.blind = Blind::Boss(BossBlind::Psychic);
+        state.select_blind().unwrap();
+
+        state.select_card(state.in_hand[0].id).unwrap();
+        assert_eq!(state.play_hand().map(|played| played.result), Ok(None));
+        assert_eq!(state.score, Score::ZERO);
+        assert_eq!(state.hands, 3);
+
+        for _ in 0..5 {
+            state.select_card(state.in_hand[0].id).unwrap();
+        }
+        state.play_hand().unwrap();
+        assert!(state.score > Score::ZERO);
+    }
+
+    #[test]
+    fn test_debuffed_cards_score_nothing() {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 10);
+        state.blind = Blind::Boss(BossBlind::Club);
+        state.select_blind().unwrap();
+
+        let is_club = |card: &Card| card.suit == CardSuit::Clubs;
+        assert!(state
+            .in_hand
+            .iter()
+            .all(|card| card.is_debuffed == is_club(card)));
+
+        // A pair of clubs is still a Pair, but only the hand's base value scores
+        let mut ids = CardIdAllocator::new();
+        let cards = [
+            ids.new_card(CardRank::Ace, CardSuit::Clubs),
+            ids.new_card(CardRank::Ace, CardSuit::Clubs),
+        ]
+        .map(|mut card| {
+            card.is_debuffed = state.is_debuffed_by_boss(&card);
+            card
+        });
+        let (hand_type, played) =
+            HandType::from_cards(cards.iter().collect(), &HandRules::default()).unwrap();
+        assert_eq!(hand_type, HandType::Pair);
+
+        state.in_hand.clear();
+        let trace = state.score_hand(&hand_type, &played, &mut Chance::Lowest);
+        assert_eq!(trace.get_score(), Score::from(20));
+    }
+
+    #[test]
+    fn test_card_status_clears_when_blind_ends() {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 10);
+        state.blind = Blind::Boss(BossBlind::House);
+        state.select_blind().unwrap();
+
+        // The House deals the first hand face down, and nothing after it
+        assert!(state.in_hand.iter().all(|card| card.is_face_down));
+        assert!(state
+            .in_hand
+            .iter()
+            .all(|card| card.get_visible_rank().is_none()));
+
+        let ids = state.in_hand[..2]
+            .iter()
+            .map(|card| card.id)
+            .collect::<Vec<_>>();
+        for id in ids {
+            state.select_card(id).unwrap();
+        }
+        state.discard_hand().unwrap();
+        assert_eq!(
+            state
+                .in_hand
+                .iter()
+                .filter(|card| !card.is_face_down)
+                .count(),
+            2
+        );
+
+        state.score = state.get_score_needed();
+        state.select_card(state.in_hand[0].id).unwrap();
+        state.play_hand().unwrap();
+        assert!(state
+            .deck
+            .draw_pile()
+            .iter()
+            .all(|card| !card.is_face_down && !card.is_debuffed));
+    }
+
+    #[test]
+    fn test_pillar_debuffs_cards_played_this_ante() {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 10);
+        state.select_blind().unwrap();
+        let played = state.in_hand[0].id;
+        state.select_card(played).unwrap();
+        state.play_hand().unwrap();
+        assert!(state.played_this_ante.contains(&played));
+
+        state.blind = Blind::Boss(BossBlind::Pillar);
+        let card = state.deck.discard_pile()[0].clone();
+        assert!(state.is_debuffed_by_boss(&card));
+    }
+
+    #[test]
+    fn test_chicot_and_perkeo() {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 10);
+        state.blind = Blind::Boss(BossBlind::Psychic);
+        state.jokers.push(JokerCard::new(JokerType::Chicot));
+        state.select_blind().unwrap();
+        assert_eq!(state.get_active_boss(), None);
+
+        // Perkeo's copy is Negative, so it fits even with every slot taken
+        state.jokers.push(JokerCard::new(JokerType::Perkeo));
+        let fool = ConsumableCard::new(Consumable::Tarot(TarotCard::TheFool));
+        state.consumables = vec![fool; 2];
+        state.score = state.get_score_needed();
+        state.select_card(state.in_hand[0].id).unwrap();
+        state.play_hand().unwrap();
+        state.collect_cash_out().unwrap();
+        state.leave_shop().unwrap();
+        assert_eq!(state.consumables.len(), 3);
+        assert_eq!(state.consumables[2].edition, CardEdition::Negative);
+    }
+
+    #[test]
+    fn test_gold_cards_count_for_interest() {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 10);
+        state.money = 4;
+        state.select_blind().unwrap();
+        state.in_hand[1].enhancement = Some(CardEnhancement::Gold);
+        state.in_hand[2].enhancement = Some(CardEnhancement::Gold);
+
+        state.score = state.get_score_needed();
+        state.select_card(state.in_hand[0].id).unwrap();
+        state.play_hand().unwrap();
+
+        let cash_out = state.cash_out.clone().unwrap();
+        assert_eq!(cash_out.gold_cards, 6);
+        assert_eq!(cash_out.interest, 2);
+        state.collect_cash_out().unwrap();
+        assert_eq!(state.money, 4 + cash_out.get_total());
+    }
+
+    #[test]
+    fn test_losing_ends_the_run() {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 10);
+        state.select_blind().unwrap();
+        state.hands = 1;
+
+        let id = state.in_hand[0].id;
+        state.select_card(id).unwrap();
+        assert_eq!(
+            state.play_hand().map(|played| played.result),
+            Ok(Some(HandResult::Lose))
+        );
+        assert_eq!(state.phase, GamePhase::GameOver);
+        assert!(state.legal_actions().is_empty());
+    }
+}
@@ -0,0 +1,284 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    ops::{Add, AddAssign, Mul, Sub},
+};
+
+/// A score or blind requirement. Stored as `mantissa * 10^exponent` so late
+/// endless antes and strong joker builds go well past what `f64` or any
+/// integer can hold without wrapping or turning into infinity.
+#[derive(Clone, Copy, Debug)]
+pub struct Score {
+    // Whole numbers up to 1e15 are stored exactly with an exponent of 0. Past
+    // that 1e14 <= |mantissa| < 1e15, so a bigger exponent is a bigger score.
+    mantissa: f64,
+    exponent: i64,
+}
+
+const MANTISSA_MAX: f64 = 1e15;
+const MANTISSA_DIGITS: i64 = 15;
+
+// Past this the game shows scores as e.g. 1.23e45
+const SCIENTIFIC_FROM: f64 = 1e11;
+
+impl Score {
+    pub const ZERO: Score = Score {
+        mantissa: 0.0,
+        exponent: 0,
+    };
+
+    // Infinity is kept as the largest `f64`, so an overflow never scores nothing
+    pub fn new(mantissa: f64, exponent: i64) -> Self {
+        debug_assert!(!mantissa.is_nan(), "score is NaN");
+        if mantissa == 0.0 || mantissa.is_nan() {
+            return Self::ZERO;
+        }
+
+        let mut mantissa = mantissa.clamp(-f64::MAX, f64::MAX);
+        let mut exponent = exponent;
+
+        if exponent < 0 {
+            mantissa /= pow10(-exponent);
+            exponent = 0;
+        }
+
+        let digits = mantissa.abs().log10().floor() as i64 + 1;
+        if digits > MANTISSA_DIGITS {
+            let shift = digits - MANTISSA_DIGITS;
+            mantissa /= pow10(shift);
+            exponent += shift;
+        } else if exponent > 0 {
+            let shift = (MANTISSA_DIGITS - digits).min(exponent);
+            mantissa *= pow10(shift);
+            exponent -= shift;
+        }
+
+        // log10 can land just either side of a power of ten
+        if mantissa.abs() >= MANTISSA_MAX {
+            mantissa /= 10.0;
+            exponent += 1;
+        } else if exponent > 0 && mantissa.abs() < MANTISSA_MAX / 10.0 {
+            mantissa *= 10.0;
+            exponent -= 1;
+        }
+
+        Self { mantissa, exponent }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mantissa == 0.0
+    }
+
+    // Infinity once the score no longer fits
+    pub fn to_f64(&self) -> f64 {
+        if self.exponent > f64::MAX_10_EXP as i64 {
+            return self.mantissa.signum() * f64::INFINITY;
+        }
+
+        self.mantissa * pow10(self.exponent)
+    }
+
+    // Scores this big are whole numbers already
+    pub fn floor(&self) -> Self {
+        if self.exponent > 0 {
+            return *self;
+        }
+
+        Self::new(self.mantissa.floor(), 0)
+    }
+
+    pub fn ceil(&self) -> Self {
+        if self.exponent > 0 {
+            return *self;
+        }
+
+        Self::new(self.mantissa.ceil(), 0)
+    }
+}
+
+fn pow10(exponent: i64) -> f64 {
+    10f64.powi(exponent.min(i32::MAX as i64) as i32)
+}
+
+impl Default for Score {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl From<f64> for Score {
+    fn from(value: f64) -> Self {
+        Self::new(value, 0)
+    }
+}
+
+impl From<u32> for Score {
+    fn from(value: u32) -> Self {
+        Self::new(value as f64, 0)
+    }
+}
+
+impl Add for Score {
+    type Output = Score;
+
+    fn add(self, other: Score) -> Score {
+        if self.is_zero() {
+            return other;
+        }
+
+        if other.is_zero() {
+            return self;
+        }
+
+        let (big, small) = if self.exponent >= other.exponent {
+            (self, other)
+        } else {
+            (other, self)
+        };
+
+        // Too small to change any digit an f64 keeps
+        let diff = big.exponent - small.exponent;
+        if diff > MANTISSA_DIGITS + 2 {
+            return big;
+        }
+
+        Score::new(big.mantissa + small.mantissa / pow10(diff), big.exponent)
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, other: Score) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Score {
+    type Output = Score;
+
+    fn sub(self, other: Score) -> Score {
+        self + Score::new(-other.mantissa, other.exponent)
+    }
+}
+
+impl Mul for Score {
+    type Output = Score;
+
+    fn mul(self, other: Score) -> Score {
+        Score::new(
+            self.mantissa * other.mantissa,
+            self.exponent + other.exponent,
+        )
+    }
+}
+
+impl Mul<f64> for Score {
+    type Output = Score;
+
+    fn mul(self, other: f64) -> Score {
+        self * Score::from(other)
+    }
+}
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let sign = |score: &Score| score.mantissa.partial_cmp(&0.0).unwrap();
+
+        match sign(self).cmp(&sign(other)) {
+            Ordering::Equal => {}
+            ordering => return ordering,
+        }
+
+        let magnitude = self
+            .exponent
+            .cmp(&other.exponent)
+            .then(self.mantissa.abs().total_cmp(&other.mantissa.abs()));
+
+        // A bigger negative number is smaller
+        match sign(self) {
+            Ordering::Less => magnitude.reverse(),
+            _ => magnitude,
+        }
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.exponent == 0 && self.mantissa.abs() < SCIENTIFIC_FROM {
+            return write!(f, "{}", self.mantissa.floor());
+        }
+
+        let shift = self.mantissa.abs().log10().floor() as i64;
+        let mut mantissa = self.mantissa / pow10(shift);
+        let mut exponent = self.exponent + shift;
+
+        // Rounding can carry into another digit, e.g. 9.9996 -> 10.000
+        if (mantissa.abs() * 1000.0).round() >= 10000.0 {
+            mantissa /= 10.0;
+            exponent += 1;
+        }
+
+        let mantissa = format!("{:.3}", mantissa);
+        let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+        write!(f, "{}e{}", mantissa, exponent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score_past_f64() {
+        let big = Score::new(5.0, 300);
+        let product = big * big;
+
+        assert_eq!(product.to_string(), "2.5e601");
+        assert!(product > big);
+        assert!(big + Score::from(1.0) == big);
+        assert!((big.to_f64() / 5e300 - 1.0).abs() < 1e-12);
+        assert_eq!(product.to_f64(), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_infinity_is_not_zero() {
+        assert!(Score::from(f64::INFINITY) > Score::new(1.0, 300));
+        assert!(Score::from(f64::NEG_INFINITY) < Score::ZERO);
+    }
+
+    #[test]
+    fn test_score_compare() {
+        let needed = Score::from(300);
+
+        assert!(Score::from(299) < needed);
+        assert!(Score::from(150) + Score::from(150) >= needed);
+        assert!(Score::from(0.5) < Score::from(1.0));
+        assert!(Score::ZERO < needed);
+        assert!(needed - Score::from(400) < Score::ZERO);
+        assert!(Score::from(-5.0) < Score::from(-1.0));
+    }
+
+    #[test]
+    fn test_score_format() {
+        assert_eq!(Score::from(0).to_string(), "0");
+        assert_eq!(Score::from(1234.7).to_string(), "1234");
+        assert_eq!(Score::from(99_999_999_999.0).to_string(), "99999999999");
+        assert_eq!(Score::new(1.23, 45).to_string(), "1.23e45");
+        assert_eq!(Score::new(1.0, 300).to_string(), "1e300");
+        assert_eq!(Score::from(123_456_789_012.0).to_string(), "1.235e11");
+        assert_eq!(Score::new(9.9999, 20).to_string(), "1e21");
+    }
+}
@@ -0,0 +1,1205 @@
+use std::{cmp::Reverse, fmt, iter};
+
+use itertools::Itertools;
+use rand::{rngs::StdRng, Rng};
+
+use crate::{
+    card::{Card, CardEdition, CardEnhancement, CardId, CardRank, CardSeal, CardSuit, FACE_CARDS},
+    error::GameError,
+    hand::{HandRules, HandType, PlayedCard},
+    joker::{JokerCard, JokerType},
+    score::Score,
+    seal::{get_seal_retriggers, GOLD_SEAL_PAYOUT},
+    GameState,
+};
+
+/// A single step applied to the running chips and mult, or something else
+/// that happens while a hand is scored.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScoreEffect {
+    Chips(f64),
+    Mult(f64),
+    XMult(f64),
+    // Paid out once the hand is scored
+    Money(f64),
+    // The card is destroyed once the hand is scored, e.g. a Glass card breaking
+    Destroy,
+    // The card's effects run again, starting with the steps that follow
+    Retrigger,
+}
+
+/// Running chips and mult while a hand is scored. Effects are applied in
+/// order, so an xmult only multiplies the mult that was added before it.
+/// Both stop at the largest `f64` rather than turning into infinity.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChipsAndMult {
+    pub chips: f64,
+    pub mult: f64,
+}
+
+impl ChipsAndMult {
+    pub fn new(chips: f64, mult: f64) -> Self {
+        Self { chips, mult }
+    }
+
+    pub fn add_chips(&mut self, chips: f64) {
+        self.chips = saturate(self.chips + chips);
+    }
+
+    pub fn add_mult(&mut self, mult: f64) {
+        self.mult = saturate(self.mult + mult);
+    }
+
+    pub fn x_mult(&mut self, x: f64) {
+        self.mult = saturate(self.mult * x);
+    }
+
+    pub fn apply(&mut self, effect: &ScoreEffect) {
+        match effect {
+            ScoreEffect::Chips(chips) => self.add_chips(*chips),
+            ScoreEffect::Mult(mult) => self.add_mult(*mult),
+            ScoreEffect::XMult(x) => self.x_mult(*x),
+            ScoreEffect::Money(_) | ScoreEffect::Destroy | ScoreEffect::Retrigger => {}
+        }
+    }
+
+    // The game rounds the final hand score down
+    pub fn get_score(&self) -> Score {
+        (Score::from(self.chips) * Score::from(self.mult)).floor()
+    }
+}
+
+fn saturate(value: f64) -> f64 {
+    value.clamp(-f64::MAX, f64::MAX)
+}
+
+/// What triggered a step while scoring.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TraceSource {
+    PlayedCard(CardId),
+    HeldCard(CardId),
+    // Index into `GameState::jokers`
+    Joker(usize, JokerType),
+}
+
+/// One scoring step, with the chips and mult after it was applied.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceStep {
+    pub source: TraceSource,
+    pub effect: ScoreEffect,
+    pub chips: f64,
+    pub mult: f64,
+}
+
+/// Every step of scoring a hand in the order the game applies them, e.g. to
+/// debug a joker or to animate the score.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScoreTrace {
+    pub hand_type: HandType,
+    pub level: u8,
+    // False if the boss refused the hand, which then scores nothing
+    pub is_allowed: bool,
+    // The hand's value at its level, before any step
+    pub base: ChipsAndMult,
+    pub steps: Vec<TraceStep>,
+    pub total: ChipsAndMult,
+}
+
+impl ScoreTrace {
+    fn new(hand_type: &HandType, level: u8, is_allowed: bool) -> Self {
+        let base = if is_allowed {
+            hand_type.get_value(level)
+        } else {
+            ChipsAndMult::new(0.0, 0.0)
+        };
+
+        Self {
+            hand_type: hand_type.clone(),
+            level,
+            is_allowed,
+            total: base.clone(),
+            base,
+            steps: vec![],
+        }
+    }
+
+    fn apply(&mut self, source: TraceSource, effect: ScoreEffect) {
+        self.total.apply(&effect);
+        self.steps.push(TraceStep {
+            source,
+            effect,
+            chips: self.total.chips,
+            mult: self.total.mult,
+        });
+    }
+
+    pub fn get_score(&self) -> Score {
+        self.total.get_score()
+    }
+
+    pub fn get_money(&self) -> f64 {
+        self.steps
+            .iter()
+            .map(|step| match step.effect {
+                ScoreEffect::Money(money) => money,
+                _ => 0.0,
+            })
+            .sum()
+    }
+
+    pub fn get_destroyed(&self) -> Vec<CardId> {
+        self.steps
+            .iter()
+            .filter_map(|step| match (&step.source, &step.effect) {
+                (TraceSource::PlayedCard(id), ScoreEffect::Destroy) => Some(*id),
+                _ => None,
+            })
+            .unique()
+            .collect()
+    }
+}
+
+impl fmt::Display for ScoreTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} lvl.{}: {} x {}",
+            self.hand_type, self.level, self.base.chips, self.base.mult
+        )?;
+        if !self.is_allowed {
+            write!(f, " (not allowed)")?;
+        }
+
+        for step in self.steps.iter() {
+            write!(
+                f,
+                "\n  {:?} {:?} -> {} x {}",
+                step.source, step.effect, step.chips, step.mult
+            )?;
+        }
+
+        write!(f, "\n  = {}", self.get_score())
+    }
+}
+
+/// How effects left to chance are settled while scoring. Playing a hand rolls
+/// for them; previews take the worst, average or best case instead.
+pub(crate) enum Chance<'a> {
+    Roll(&'a mut StdRng),
+    Lowest,
+    Expected,
+    Highest,
+}
+
+impl Chance<'_> {
+    // A whole number from low to high, inclusive
+    fn range(&mut self, low: i32, high: i32) -> f64 {
+        match self {
+            Chance::Roll(rng) => rng.gen_range(low..=high) as f64,
+            Chance::Lowest => low as f64,
+            Chance::Expected => (low + high) as f64 / 2.0,
+            Chance::Highest => high as f64,
+        }
+    }
+
+    // Whether a 1 in `odds` chance comes up. Previews only count on it in the
+    // best case
+    fn happens(&mut self, odds: u32) -> bool {
+        match self {
+            Chance::Roll(rng) => rng.gen_range(0..odds) == 0,
+            Chance::Lowest | Chance::Expected => false,
+            Chance::Highest => true,
+        }
+    }
+
+    // `value` on a 1 in `odds` chance, or its average when expecting
+    fn roll(&mut self, odds: u32, value: f64) -> f64 {
+        if matches!(self, Chance::Expected) {
+            value / odds as f64
+        } else if self.happens(odds) {
+            value
+        } else {
+            0.0
+        }
+    }
+}
+
+/// What a selection would score if played now. Anything left to chance is
+/// given as its lowest, expected and highest outcome.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HandPreview {
+    pub hand_type: HandType,
+    pub scoring: Vec<CardId>,
+    pub lowest: ChipsAndMult,
+    pub expected: ChipsAndMult,
+    pub highest: ChipsAndMult,
+}
+
+impl HandPreview {
+    pub fn is_certain(&self) -> bool {
+        self.lowest == self.highest
+    }
+}
+
+/// A hand that could be played from the cards in hand, and what it would
+/// score right now.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Play {
+    pub hand_type: HandType,
+    pub cards: Vec<CardId>,
+    pub score: Score,
+}
+
+impl GameState {
+    /// Previews playing the given cards, which must be in hand or selected,
+    /// without changing the game. Face down cards can't be previewed, as the
+    /// result would give away what they are.
+    pub fn preview_hand(&self, card_ids: &[CardId]) -> Result<HandPreview, GameError> {
+        let limit = self.get_selection_limit();
+        if card_ids.len() > limit {
+            return Err(GameError::TooManyCardsSelected(limit));
+        }
+        if let Some(id) = card_ids.iter().duplicates().next() {
+            return Err(GameError::DuplicateCard(*id));
+        }
+
+        let cards = card_ids
+            .iter()
+            .map(|id| {
+                self.in_hand
+                    .iter()
+                    .chain(self.selected_cards.iter())
+                    .find(|card| card.id == *id)
+                    .ok_or(GameError::CardNotInHand(*id))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(card) = cards.iter().find(|card| card.is_face_down) {
+            return Err(GameError::CardFaceDown(card.id));
+        }
+
+        let (hand_type, played) = HandType::from_cards(cards, &self.get_hand_rules())?;
+        let preview = |mut chance| self.preview_play(&hand_type, &played, &mut chance);
+
+        Ok(HandPreview {
+            scoring: get_scoring_cards(&played).map(|card| card.id).collect(),
+            lowest: preview(Chance::Lowest),
+            expected: preview(Chance::Expected),
+            highest: preview(Chance::Highest),
+            hand_type: hand_type.clone(),
+        })
+    }
+
+    /// Each distinct play the cards in hand allow, highest expected score
+    /// first. On a tie the play found first, i.e. the one with fewer cards,
+    /// comes first.
+    /// Face down cards are left out, as the player can't know what they make.
+    pub fn get_best_plays(&self) -> Vec<Play> {
+        let cards = self
+            .in_hand
+            .iter()
+            .chain(self.selected_cards.iter())
+            .filter(|card| !card.is_face_down)
+            .collect::<Vec<_>>();
+
+        let limit = self.get_selection_limit();
+        let mut plays = HandType::get_possible_hands(cards, &self.get_hand_rules())
+            .into_iter()
+            .filter(|(_, played)| played.len() <= limit)
+            .map(|(hand_type, played)| Play {
+                score: self
+                    .preview_play(&hand_type, &played, &mut Chance::Expected)
+                    .get_score(),
+                cards: played.iter().map(|played| played.card.id).collect(),
+                hand_type,
+            })
+            .collect::<Vec<_>>();
+
+        plays.sort_by_key(|play| Reverse(play.score));
+        plays
+    }
+
+    // Scores the cards as if they were played, with the cards left in hand and
+    // the jokers updated as they would be, and returns what the hand scores
+    fn preview_play(
+        &self,
+        hand_type: &HandType,
+        played: &[PlayedCard],
+        chance: &mut Chance,
+    ) -> ChipsAndMult {
+        let held = self
+            .in_hand
+            .iter()
+            .chain(self.selected_cards.iter())
+            .filter(|card| !played.iter().any(|played| played.card.id == card.id))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let jokers = if self.is_hand_allowed(played) {
+            self.get_jokers_before_scoring(hand_type, played)
+        } else {
+            self.jokers.clone()
+        };
+
+        self.score_hand_with(hand_type, played, &held, &jokers, chance)
+            .total
+    }
+
+    /// Scores a played hand without changing the game. The order matches the
+    /// game: the hand's base value, then each scoring card left to right, then
+    /// cards held in hand, then jokers left to right.
+    pub(crate) fn score_hand(
+        &self,
+        hand_type: &HandType,
+        played: &[PlayedCard],
+        chance: &mut Chance,
+    ) -> ScoreTrace {
+        self.score_hand_with(hand_type, played, &self.in_hand, &self.jokers, chance)
+    }
+
+    // Scores with the given cards held and jokers in play in place of the
+    // game's own, so a preview doesn't need a copy of the whole game
+    fn score_hand_with(
+        &self,
+        hand_type: &HandType,
+        played: &[PlayedCard],
+        held: &[Card],
+        jokers: &[JokerCard],
+        chance: &mut Chance,
+    ) -> ScoreTrace {
+        let is_allowed = self.is_hand_allowed(played);
+        let mut trace = ScoreTrace::new(hand_type, self.get_hand_level(hand_type), is_allowed);
+        if !is_allowed {
+            return trace;
+        }
+
+        let rules = self.get_hand_rules();
+
+        // Each retrigger runs the card's effects again, rolling anything random anew
+        for card in get_scoring_cards(played) {
+            let retriggers = self.get_retriggers(card, false).into_iter().map(Some);
+            for retrigger in iter::once(None).chain(retriggers) {
+                if let Some(source) = retrigger {
+                    trace.apply(source, ScoreEffect::Retrigger);
+                }
+                for effect in self.get_scored_card_effects(card, &rules, chance) {
+                    trace.apply(TraceSource::PlayedCard(card.id), effect);
+                }
+            }
+        }
+
+        for card in held.iter().filter(|card| !card.is_debuffed) {
+            let retriggers = self.get_retriggers(card, true).into_iter().map(Some);
+            for retrigger in iter::once(None).chain(retriggers) {
+                if let Some(source) = retrigger {
+                    trace.apply(source, ScoreEffect::Retrigger);
+                }
+                for effect in self.get_held_card_effects(card, held) {
+                    trace.apply(TraceSource::HeldCard(card.id), effect);
+                }
+            }
+        }
+
+        for (idx, joker) in jokers.iter().enumerate() {
+            // Foil and Holographic add before the joker's own effects, Polychrome
+            // multiplies after them
+            let edition = get_edition_effect(&joker.edition);
+            let (before, after) = match joker.edition {
+                CardEdition::Polychrome => (None, edition),
+                _ => (edition, None),
+            };
+
+            let effects = self.get_joker_effects(joker, hand_type, played, held, &rules, chance);
+            for effect in before.into_iter().chain(effects).chain(after) {
+                trace.apply(TraceSource::Joker(idx, joker.joker.clone()), effect);
+            }
+        }
+
+        trace
+    }
+
+    // What makes a scored or held card's effects run again, once per entry
+    pub(crate) fn get_retriggers(&self, card: &Card, is_held: bool) -> Vec<TraceSource> {
+        let source = if is_held {
+            TraceSource::HeldCard(card.id)
+        } else {
+            TraceSource::PlayedCard(card.id)
+        };
+        let mut retriggers = vec![source; get_seal_retriggers(card)];
+
+        for (idx, joker) in self.jokers.iter().enumerate() {
+            let retriggers_card = match joker.joker {
+                JokerType::Mime => is_held,
+                JokerType::Hack => {
+                    !is_held
+                        && card.has_rank()
+                        && matches!(
+                            card.rank,
+                            CardRank::Two | CardRank::Three | CardRank::Four | CardRank::Five
+                        )
+                }
+                // Scoring runs before the hand is taken off, so one left is the last
+                JokerType::Dusk => !is_held && self.hands == 1,
+                _ => false,
+            };
+
+            if retriggers_card {
+                retriggers.push(TraceSource::Joker(idx, joker.joker.clone()));
+            }
+        }
+
+        retriggers
+    }
+
+    pub fn is_face_card(&self, card: &Card) -> bool {
+        if !card.has_rank() {
+            return false;
+        }
+
+        FACE_CARDS.contains(&card.rank) || self.has_joker(&JokerType::Pareidolia)
+    }
+
+    pub fn has_joker(&self, joker: &JokerType) -> bool {
+        self.jokers.iter().any(|owned| owned.joker.is_same(joker))
+    }
+
+    pub fn get_hand_rules(&self) -> HandRules {
+        HandRules {
+            four_card_hands: self.has_joker(&JokerType::FourFingers),
+            straight_gaps: self.has_joker(&JokerType::Shortcut),
+            merged_suits: self.has_joker(&JokerType::Smeared),
+            wild_suits: true,
+            all_cards_score: self.has_joker(&JokerType::Splash),
+        }
+    }
+
+    fn get_scored_card_effects(
+        &self,
+        card: &Card,
+        rules: &HandRules,
+        chance: &mut Chance,
+    ) -> Vec<ScoreEffect> {
+        let chips = if card.has_rank() {
+            card.rank.get_base_chips()
+        } else {
+            50
+        };
+        let mut effects = vec![ScoreEffect::Chips((chips + card.extra_chips) as f64)];
+
+        match card.enhancement {
+            Some(CardEnhancement::Bonus) => effects.push(ScoreEffect::Chips(30.0)),
+            Some(CardEnhancement::Mult) => effects.push(ScoreEffect::Mult(4.0)),
+            Some(CardEnhancement::Glass) => {
+                effects.push(ScoreEffect::XMult(2.0));
+                if chance.happens(4) {
+                    effects.push(ScoreEffect::Destroy);
+                }
+            }
+            Some(CardEnhancement::Lucky) => {
+                let mult = chance.roll(5, 20.0);
+                if mult > 0.0 {
+                    effects.push(ScoreEffect::Mult(mult));
+                }
+
+                let money = chance.roll(15, 20.0);
+                if money > 0.0 {
+                    effects.push(ScoreEffect::Money(money));
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(edition) = &card.edition {
+            effects.extend(get_edition_effect(edition));
+        }
+
+        if card.seal == Some(CardSeal::Gold) {
+            effects.push(ScoreEffect::Money(GOLD_SEAL_PAYOUT as f64));
+        }
+
+        // Everything else looks at the card's rank or suit
+        if !card.has_rank() {
+            return effects;
+        }
+
+        for joker in self.jokers.iter() {
+            let suit_mult = |suit: CardSuit| {
+                if rules.is_suit(card, &suit) {
+                    vec![ScoreEffect::Mult(3.0)]
+                } else {
+                    vec![]
+                }
+            };
+
+            effects.extend(match joker.joker {
+                JokerType::Greedy => suit_mult(CardSuit::Diamonds),
+                JokerType::Lusty => suit_mult(CardSuit::Hearts),
+                JokerType::Wrathful => suit_mult(CardSuit::Spades),
+                JokerType::Gluttonous => suit_mult(CardSuit::Clubs),
+                JokerType::ScaryFace if self.is_face_card(card) => {
+                    vec![ScoreEffect::Chips(30.0)]
+                }
+                JokerType::EvenSteven if is_even(&card.rank) => vec![ScoreEffect::Mult(4.0)],
+                JokerType::OddTodd if is_odd(&card.rank) => vec![ScoreEffect::Chips(31.0)],
+                JokerType::Scholar if card.rank == CardRank::Ace => {
+                    vec![ScoreEffect::Chips(20.0), ScoreEffect::Mult(4.0)]
+                }
+                JokerType::Fibonacci if is_fibonacci(&card.rank) => {
+                    vec![ScoreEffect::Mult(8.0)]
+                }
+                JokerType::Triboulet if matches!(card.rank, CardRank::King | CardRank::Queen) => {
+                    vec![ScoreEffect::XMult(2.0)]
+                }
+                _ => vec![],
+            });
+        }
+
+        effects
+    }
+
+    fn get_held_card_effects(&self, card: &Card, held: &[Card]) -> Vec<ScoreEffect> {
+        let mut effects = vec![];
+
+        if card.enhancement == Some(CardEnhancement::Steel) {
+            effects.push(ScoreEffect::XMult(1.5));
+        }
+
+        for joker in self.jokers.iter() {
+            if joker.joker == JokerType::RaisedFist {
+                // Only the lowest ranked card held, the first one on a tie
+                let lowest = held
+                    .iter()
+                    .filter(|card| card.has_rank())
+                    .min_by(|a, b| a.rank.cmp(&b.rank));
+                if lowest.map(|lowest| lowest.id) == Some(card.id) {
+                    let mult = 2 * card.rank.get_base_chips();
+                    effects.push(ScoreEffect::Mult(mult as f64));
+                }
+            }
+        }
+
+        effects
+    }
+
+    fn get_joker_effects(
+        &self,
+        joker: &JokerCard,
+        hand_type: &HandType,
+        played: &[PlayedCard],
+        held: &[Card],
+        rules: &HandRules,
+        chance: &mut Chance,
+    ) -> Vec<ScoreEffect> {
+        let if_contains = |other: HandType, effect: ScoreEffect| {
+            if hand_type.contains(&other) {
+                vec![effect]
+            } else {
+                vec![]
+            }
+        };
+
+        match &joker.joker {
+            JokerType::Joker => vec![ScoreEffect::Mult(4.0)],
+
+            JokerType::Jolly => if_contains(HandType::Pair, ScoreEffect::Mult(8.0)),
+            JokerType::Zany => if_contains(HandType::ThreeOfAKind, ScoreEffect::Mult(12.0)),
+            JokerType::Mad => if_contains(HandType::TwoPair, ScoreEffect::Mult(10.0)),
+            JokerType::Crazy => if_contains(HandType::Straight, ScoreEffect::Mult(12.0)),
+            JokerType::Droll => if_contains(HandType::Flush, ScoreEffect::Mult(10.0)),
+
+            JokerType::Sly => if_contains(HandType::Pair, ScoreEffect::Chips(50.0)),
+            JokerType::Wily => if_contains(HandType::ThreeOfAKind, ScoreEffect::Chips(100.0)),
+            JokerType::Clever => if_contains(HandType::TwoPair, ScoreEffect::Chips(80.0)),
+            JokerType::Devious => if_contains(HandType::Straight, ScoreEffect::Chips(100.0)),
+            JokerType::Crafty => if_contains(HandType::Flush, ScoreEffect::Chips(80.0)),
+
+            JokerType::Half if played.len() <= 3 => vec![ScoreEffect::Mult(20.0)],
+            JokerType::Banner => vec![ScoreEffect::Chips(30.0 * self.discards as f64)],
+            JokerType::MysticSummit if self.discards == 0 => vec![ScoreEffect::Mult(15.0)],
+            JokerType::Misprint => vec![ScoreEffect::Mult(chance.range(0, 23))],
+            JokerType::Abstract => vec![ScoreEffect::Mult(3.0 * self.jokers.len() as f64)],
+            JokerType::GrosMichel => vec![ScoreEffect::Mult(15.0)],
+            JokerType::RideTheBus(mult) => vec![ScoreEffect::Mult(*mult as f64)],
+            JokerType::Runner(chips) => vec![ScoreEffect::Chips(*chips as f64)],
+            JokerType::IceCream(chips) => vec![ScoreEffect::Chips(*chips as f64)],
+            JokerType::Blue => {
+                vec![ScoreEffect::Chips(2.0 * self.deck.draw_pile().len() as f64)]
+            }
+
+            JokerType::Stencil => {
+                // Counts itself as an empty slot
+                let empty = self.get_joker_slots().saturating_sub(self.jokers.len());
+                let stencils = self
+                    .jokers
+                    .iter()
+                    .filter(|joker| joker.joker == JokerType::Stencil)
+                    .count();
+                vec![ScoreEffect::XMult((empty + stencils) as f64)]
+            }
+            JokerType::Loyalty(0) => vec![ScoreEffect::XMult(4.0)],
+            JokerType::Canio(destroyed) if *destroyed > 0 => {
+                vec![ScoreEffect::XMult(1.0 + *destroyed as f64)]
+            }
+            // x1 more for every 23 cards discarded
+            JokerType::Yorick(discarded) if *discarded >= 23 => {
+                vec![ScoreEffect::XMult(1.0 + (*discarded / 23) as f64)]
+            }
+            JokerType::Steel => {
+                let steel = held
+                    .iter()
+                    .chain(played.iter().map(|played| played.card))
+                    .chain(self.deck.draw_pile().iter())
+                    .chain(self.deck.discard_pile().iter())
+                    .filter(|card| card.enhancement == Some(CardEnhancement::Steel))
+                    .count();
+                vec![ScoreEffect::XMult(1.0 + 0.2 * steel as f64)]
+            }
+            JokerType::Blackboard => {
+                let all_black = held.iter().all(|card| {
+                    rules.is_suit(card, &CardSuit::Spades) || rules.is_suit(card, &CardSuit::Clubs)
+                });
+                if all_black {
+                    vec![ScoreEffect::XMult(3.0)]
+                } else {
+                    vec![]
+                }
+            }
+
+            _ => vec![],
+        }
+    }
+
+    // Jokers that grow before the hand is scored, so the new value counts
+    pub(crate) fn update_jokers_before_scoring(
+        &mut self,
+        hand_type: &HandType,
+        played: &[PlayedCard],
+    ) {
+        self.jokers = self.get_jokers_before_scoring(hand_type, played);
+    }
+
+    fn get_jokers_before_scoring(
+        &self,
+        hand_type: &HandType,
+        played: &[PlayedCard],
+    ) -> Vec<JokerCard> {
+        let has_face = get_scoring_cards(played).any(|card| self.is_face_card(card));
+
+        let mut jokers = self.jokers.clone();
+        for joker in jokers.iter_mut() {
+            match &mut joker.joker {
+                JokerType::Runner(chips) if hand_type.contains(&HandType::Straight) => {
+                    *chips = chips.saturating_add(15);
+                }
+                JokerType::RideTheBus(mult) => {
+                    *mult = if has_face { 0 } else { mult.saturating_add(1) };
+                }
+                _ => {}
+            }
+        }
+
+        jokers
+    }
+
+    // Jokers that change once the hand has been scored
+    pub(crate) fn update_jokers_after_scoring(&mut self, played: &[PlayedCard]) {
+        let has_hiker = self.has_joker(&JokerType::Hiker);
+
+        for joker in self.jokers.iter_mut() {
+            match &mut joker.joker {
+                JokerType::IceCream(chips) => *chips = chips.saturating_sub(5),
+                JokerType::Loyalty(countdown) => {
+                    *countdown = if *countdown == 0 { 5 } else { *countdown - 1 };
+                }
+                _ => {}
+            }
+        }
+
+        // Melted ice cream is gone
+        self.jokers
+            .retain(|joker| joker.joker != JokerType::IceCream(0));
+
+        if has_hiker {
+            let hikers = self
+                .jokers
+                .iter()
+                .filter(|joker| joker.joker == JokerType::Hiker)
+                .count() as u32;
+            let scored = get_scoring_cards(played)
+                .map(|card| card.id)
+                .collect::<Vec<_>>();
+            for card in self.selected_cards.iter_mut() {
+                if scored.contains(&card.id) {
+                    card.extra_chips += 5 * hikers;
+                }
+            }
+        }
+    }
+
+    pub(crate) fn update_jokers_on_discard(&mut self, discarded: usize) {
+        for joker in self.jokers.iter_mut() {
+            if let JokerType::Yorick(count) = &mut joker.joker {
+                *count = count.saturating_add(discarded as u16);
+            }
+        }
+    }
+}
+
+// Foil, Holographic and Polychrome score; Negative only changes slots
+fn get_edition_effect(edition: &CardEdition) -> Option<ScoreEffect> {
+    match edition {
+        CardEdition::Foil => Some(ScoreEffect::Chips(50.0)),
+        CardEdition::Holographic => Some(ScoreEffect::Mult(10.0)),
+        CardEdition::Polychrome => Some(ScoreEffect::XMult(1.5)),
+        CardEdition::Base | CardEdition::Negative => None,
+    }
+}
+
+// Debuffed cards still make up the hand, but are left out of scoring
+fn get_scoring_cards<'a>(played: &'a [PlayedCard]) -> impl Iterator<Item = &'a Card> {
+    played
+        .iter()
+        .filter(|played| played.is_scoring && !played.card.is_debuffed)
+        .map(|played| played.card)
+}
+
+fn is_even(rank: &CardRank) -> bool {
+    matches!(
+        rank,
+        CardRank::Two | CardRank::Four | CardRank::Six | CardRank::Eight | CardRank::Ten
+    )
+}
+
+fn is_odd(rank: &CardRank) -> bool {
+    matches!(
+        rank,
+        CardRank::Ace | CardRank::Three | CardRank::Five | CardRank::Seven | CardRank::Nine
+    )
+}
+
+fn is_fibonacci(rank: &CardRank) -> bool {
+    matches!(
+        rank,
+        CardRank::Ace | CardRank::Two | CardRank::Three | CardRank::Five | CardRank::Eight
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        card::CardIdAllocator, new_test_game, rng::RngStream, stake::GameStake, GameStartingDeck,
+    };
+
+    fn new_game() -> GameState {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 0);
+        state.in_hand.clear();
+        state
+    }
+
+    #[test]
+    fn test_best_plays() {
+        let mut state = new_game();
+        let mut ids = CardIdAllocator::new();
+        state.in_hand = vec![
+            ids.new_card(CardRank::Two, CardSuit::Hearts),
+            ids.new_card(CardRank::King, CardSuit::Spades),
+            ids.new_card(CardRank::Two, CardSuit::Clubs),
+            ids.new_card(CardRank::King, CardSuit::Hearts),
+            ids.new_card(CardRank::Two, CardSuit::Spades),
+            ids.new_card(CardRank::Nine, CardSuit::Diamonds),
+        ];
+
+        let plays = state.get_best_plays();
+        // 6 High Cards, 4 Pairs, 3 Two Pairs, a Three of a Kind and a Full House
+        assert_eq!(plays.len(), 15);
+
+        // Full House is 40 x 4, plus 2 + 2 + 2 + 10 + 10
+        let best = &plays[0];
+        assert_eq!(best.hand_type, HandType::FullHouse);
+        assert_eq!(best.cards, vec![0, 1, 2, 3, 4]);
+        assert_eq!(best.score, Score::from(66 * 4));
+
+        // Jokers count towards each play's score
+        state.jokers.push(JokerCard::new(JokerType::Jolly));
+        let plays = state.get_best_plays();
+        let kings = plays.iter().find(|play| play.cards == vec![1, 3]).unwrap();
+        assert_eq!(kings.hand_type, HandType::Pair);
+        assert_eq!(kings.score, Score::from(30 * (2 + 8)));
+        assert!(plays.windows(2).all(|pair| pair[0].score >= pair[1].score));
+    }
+
+    #[test]
+    fn test_preview_hand() {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 0);
+        state.select_blind().unwrap();
+        let ids = state.in_hand[..2]
+            .iter()
+            .map(|card| card.id)
+            .collect::<Vec<_>>();
+
+        // Previewing changes nothing, not even the random streams
+        let mut before = state.clone();
+        let preview = state.preview_hand(&ids).unwrap();
+        assert_eq!(format!("{:?}", state), format!("{:?}", before));
+        let roll = |state: &mut GameState| state.rng.stream(RngStream::Probability).gen::<u64>();
+        assert_eq!(roll(&mut state), roll(&mut before));
+        assert!(preview.is_certain());
+
+        state.jokers.push(JokerCard::new(JokerType::Misprint));
+        let preview = state.preview_hand(&ids).unwrap();
+        assert!(!preview.is_certain());
+        assert_eq!(preview.highest.mult - preview.lowest.mult, 23.0);
+        assert_eq!(preview.expected.mult - preview.lowest.mult, 11.5);
+        assert_eq!(preview.lowest.chips, preview.highest.chips);
+
+        // The real play lands somewhere in the range
+        for id in ids.iter() {
+            state.select_card(*id).unwrap();
+        }
+        state.play_hand().unwrap();
+        assert!(state.score >= preview.lowest.get_score());
+        assert!(state.score <= preview.highest.get_score());
+
+        assert_eq!(
+            state.preview_hand(&[ids[0]]),
+            Err(GameError::CardNotInHand(ids[0]))
+        );
+        assert_eq!(state.preview_hand(&[]), Err(GameError::NoCardsSelected));
+
+        let id = state.in_hand[0].id;
+        assert_eq!(
+            state.preview_hand(&[id, id]),
+            Err(GameError::DuplicateCard(id))
+        );
+        let ids = state.in_hand.iter().map(|card| card.id).collect::<Vec<_>>();
+        assert_eq!(
+            state.preview_hand(&ids[..6]),
+            Err(GameError::TooManyCardsSelected(5))
+        );
+    }
+
+    #[test]
+    fn test_face_down_cards_are_not_previewed() {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 0);
+        state.select_blind().unwrap();
+        state.in_hand[0].is_face_down = true;
+        let hidden = state.in_hand[0].id;
+
+        assert_eq!(
+            state.preview_hand(&[hidden, state.in_hand[1].id]),
+            Err(GameError::CardFaceDown(hidden))
+        );
+        let plays = state.get_best_plays();
+        assert!(!plays.is_empty());
+        assert!(plays.iter().all(|play| !play.cards.contains(&hidden)));
+    }
+
+    #[test]
+    fn test_preview_matches_play() {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 0);
+        state.select_blind().unwrap();
+        state.jokers.push(JokerCard::new(JokerType::RideTheBus(3)));
+        state.jokers.push(JokerCard::new(JokerType::Runner(0)));
+        state.jokers.push(JokerCard::new(JokerType::RaisedFist));
+
+        // A card selected but not played is still held when the hand scores
+        let held = state.in_hand[7].id;
+        state.select_card(held).unwrap();
+        let ids = state.in_hand[..2]
+            .iter()
+            .map(|card| card.id)
+            .collect::<Vec<_>>();
+        let preview = state.preview_hand(&ids).unwrap();
+        assert!(preview.is_certain());
+
+        state.deselect_card(held).unwrap();
+        for id in ids.iter() {
+            state.select_card(*id).unwrap();
+        }
+        state.play_hand().unwrap();
+        assert_eq!(state.score, preview.expected.get_score());
+    }
+
+    #[test]
+    fn test_xmult_applies_in_order() {
+        let mut score = ChipsAndMult::new(10.0, 1.0);
+        score.add_mult(3.0);
+        score.x_mult(1.5);
+        score.add_mult(1.0);
+
+        assert_eq!(score.mult, 7.0);
+        assert_eq!(score.get_score(), Score::from(70));
+    }
+
+    #[test]
+    fn test_chips_and_mult_saturate() {
+        let mut score = ChipsAndMult::new(10.0, 1.0);
+        for _ in 0..4 {
+            score.x_mult(1e100);
+        }
+
+        assert!(score.mult.is_finite());
+        assert!(score.get_score() > Score::new(1.0, 300));
+    }
+
+    #[test]
+    fn test_score_hand_order() {
+        let mut state = new_game();
+        let mut ids = CardIdAllocator::new();
+        let cards = [
+            ids.new_card(CardRank::Ace, CardSuit::Hearts),
+            ids.new_card(CardRank::Ace, CardSuit::Spades),
+        ];
+        let (_, played) =
+            HandType::from_cards(cards.iter().collect(), &HandRules::default()).unwrap();
+
+        // Pair is 10 x 2, each ace adds 11 chips
+        state.jokers.push(JokerCard::new(JokerType::Stencil));
+        state.jokers.push(JokerCard::new(JokerType::Joker));
+        let mut rng = StdRng::seed_from_u64(0);
+        let score = state
+            .score_hand(&HandType::Pair, &played, &mut Chance::Roll(&mut rng))
+            .total;
+        assert_eq!(score, ChipsAndMult::new(32.0, (2.0 * 4.0) + 4.0));
+
+        // Jokers run left to right, so the xmult now also covers the +4
+        state.jokers.reverse();
+        let score = state
+            .score_hand(&HandType::Pair, &played, &mut Chance::Roll(&mut rng))
+            .total;
+        assert_eq!(score, ChipsAndMult::new(32.0, (2.0 + 4.0) * 4.0));
+    }
+
+    #[test]
+    fn test_score_trace() {
+        let mut state = new_game();
+        let mut ids = CardIdAllocator::new();
+        let cards = [
+            ids.new_card(CardRank::King, CardSuit::Hearts),
+            ids.new_card(CardRank::King, CardSuit::Spades),
+            ids.new_card(CardRank::Two, CardSuit::Spades),
+        ];
+        state.in_hand = vec![ids.new_card(CardRank::Three, CardSuit::Clubs)];
+        state.jokers.push(JokerCard::new(JokerType::RaisedFist));
+        state.jokers.push(JokerCard::new(JokerType::Jolly));
+        state.level_up_hand(HandType::Pair);
+
+        let (_, played) =
+            HandType::from_cards(cards.iter().collect(), &HandRules::default()).unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+        let trace = state.score_hand(&HandType::Pair, &played, &mut Chance::Roll(&mut rng));
+
+        // Pair at level 2 is 25 x 3, and the Two is a kicker
+        assert_eq!(trace.level, 2);
+        assert_eq!(trace.base, ChipsAndMult::new(25.0, 3.0));
+
+        let steps = trace
+            .steps
+            .iter()
+            .map(|step| (step.source.clone(), step.chips, step.mult))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            steps,
+            vec![
+                (TraceSource::PlayedCard(0), 35.0, 3.0),
+                (TraceSource::PlayedCard(1), 45.0, 3.0),
+                (TraceSource::HeldCard(3), 45.0, 9.0),
+                (TraceSource::Joker(1, JokerType::Jolly), 45.0, 17.0),
+            ]
+        );
+        assert_eq!(trace.get_score(), Score::from(45 * 17));
+    }
+
+    #[test]
+    fn test_card_enhancements() {
+        let mut state = new_game();
+        let mut ids = CardIdAllocator::new();
+        let mut cards = [
+            ids.new_card(CardRank::Ten, CardSuit::Hearts),
+            ids.new_card(CardRank::Ten, CardSuit::Spades),
+            ids.new_card(CardRank::Ace, CardSuit::Clubs),
+        ];
+        cards[0].enhancement = Some(CardEnhancement::Glass);
+        cards[1].enhancement = Some(CardEnhancement::Lucky);
+        cards[2].enhancement = Some(CardEnhancement::Stone);
+
+        let mut steel = ids.new_card(CardRank::Two, CardSuit::Clubs);
+        steel.enhancement = Some(CardEnhancement::Steel);
+        state.in_hand = vec![steel];
+
+        // The Stone card has no rank, but scores 50 chips along with the Pair
+        let (hand_type, played) =
+            HandType::from_cards(cards.iter().collect(), &HandRules::default()).unwrap();
+        assert_eq!(hand_type, HandType::Pair);
+        assert!(played.iter().all(|played| played.is_scoring));
+
+        let trace = |mut chance| state.score_hand(&hand_type, &played, &mut chance);
+
+        // Pair is 10 x 2, Glass doubles the mult and Steel held is x1.5
+        let lowest = trace(Chance::Lowest);
+        assert_eq!(lowest.total, ChipsAndMult::new(80.0, 2.0 * 2.0 * 1.5));
+        assert_eq!(lowest.get_money(), 0.0);
+        assert!(lowest.get_destroyed().is_empty());
+
+        let highest = trace(Chance::Highest);
+        assert_eq!(
+            highest.total,
+            ChipsAndMult::new(80.0, (2.0 * 2.0 + 20.0) * 1.5)
+        );
+        assert_eq!(highest.get_money(), 20.0);
+        assert_eq!(highest.get_destroyed(), vec![0]);
+
+        let expected = trace(Chance::Expected);
+        assert_eq!(expected.total.mult, (2.0 * 2.0 + 4.0) * 1.5);
+        assert!(expected.get_destroyed().is_empty());
+
+        // Only Stone cards make a High Card with nothing but the stones in it
+        let stones = &cards[2..];
+        let (hand_type, played) =
+            HandType::from_cards(stones.iter().collect(), &HandRules::default()).unwrap();
+        assert_eq!(hand_type, HandType::HighCard);
+        let score = state.score_hand(&hand_type, &played, &mut Chance::Lowest);
+        assert_eq!(score.total, ChipsAndMult::new(55.0, 1.5));
+    }
+
+    #[test]
+    fn test_card_editions() {
+        let mut state = new_game();
+        let mut ids = CardIdAllocator::new();
+        let mut cards = [
+            ids.new_card(CardRank::Ace, CardSuit::Hearts),
+            ids.new_card(CardRank::Ace, CardSuit::Spades),
+        ];
+        cards[0].edition = Some(CardEdition::Foil);
+        cards[1].edition = Some(CardEdition::Polychrome);
+        let (_, played) =
+            HandType::from_cards(cards.iter().collect(), &HandRules::default()).unwrap();
+
+        // Pair is 10 x 2, Foil adds 50 chips and Polychrome is x1.5
+        let score = |state: &GameState| {
+            state
+                .score_hand(&HandType::Pair, &played, &mut Chance::Lowest)
+                .total
+        };
+        assert_eq!(score(&state), ChipsAndMult::new(82.0, 3.0));
+
+        // A Polychrome joker multiplies after its own +4, a Holographic one adds first
+        let mut joker = JokerCard::new(JokerType::Joker);
+        joker.edition = CardEdition::Polychrome;
+        state.jokers.push(joker);
+        assert_eq!(score(&state), ChipsAndMult::new(82.0, (3.0 + 4.0) * 1.5));
+
+        state.jokers[0].edition = CardEdition::Holographic;
+        assert_eq!(score(&state), ChipsAndMult::new(82.0, 3.0 + 10.0 + 4.0));
+
+        // Negative scores nothing
+        state.jokers[0].edition = CardEdition::Negative;
+        assert_eq!(score(&state), ChipsAndMult::new(82.0, 3.0 + 4.0));
+    }
+
+    #[test]
+    fn test_retriggers() {
+        let mut state = new_game();
+        let mut ids = CardIdAllocator::new();
+        let mut cards = [
+            ids.new_card(CardRank::Two, CardSuit::Hearts),
+            ids.new_card(CardRank::Two, CardSuit::Spades),
+        ];
+        cards[0].seal = Some(CardSeal::Red);
+        let (_, played) =
+            HandType::from_cards(cards.iter().collect(), &HandRules::default()).unwrap();
+
+        let mut steel = ids.new_card(CardRank::King, CardSuit::Clubs);
+        steel.enhancement = Some(CardEnhancement::Steel);
+        state.in_hand = vec![steel];
+
+        state.jokers.push(JokerCard::new(JokerType::Hack));
+        state.jokers.push(JokerCard::new(JokerType::Mime));
+        state.jokers.push(JokerCard::new(JokerType::Dusk));
+        state.hands = 2;
+
+        // Hack runs both twos again, and the Red seal the first one a third
+        // time. Mime runs the Steel card again
+        let trace = state.score_hand(&HandType::Pair, &played, &mut Chance::Lowest);
+        assert_eq!(
+            trace.total,
+            ChipsAndMult::new(10.0 + 5.0 * 2.0, 2.0 * 1.5 * 1.5)
+        );
+
+        let retriggers = trace
+            .steps
+            .iter()
+            .filter(|step| step.effect == ScoreEffect::Retrigger)
+            .map(|step| step.source.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            retriggers,
+            vec![
+                TraceSource::PlayedCard(cards[0].id),
+                TraceSource::Joker(0, JokerType::Hack),
+                TraceSource::Joker(0, JokerType::Hack),
+                TraceSource::Joker(1, JokerType::Mime),
+            ]
+        );
+
+        // Dusk runs every played card again on the last hand
+        state.hands = 1;
+        let trace = state.score_hand(&HandType::Pair, &played, &mut Chance::Lowest);
+        assert_eq!(trace.total.chips, 10.0 + 2.0 * 7.0);
+    }
+
+    #[test]
+    fn test_legendary_jokers() {
+        let mut state = new_game();
+        let mut ids = CardIdAllocator::new();
+        let cards = [
+            ids.new_card(CardRank::King, CardSuit::Hearts),
+            ids.new_card(CardRank::King, CardSuit::Spades),
+        ];
+        let (_, played) =
+            HandType::from_cards(cards.iter().collect(), &HandRules::default()).unwrap();
+        let mult = |state: &GameState| {
+            state
+                .score_hand(&HandType::Pair, &played, &mut Chance::Lowest)
+                .total
+                .mult
+        };
+
+        // Triboulet doubles the mult for each King or Queen scored
+        state.jokers = vec![JokerCard::new(JokerType::Triboulet)];
+        assert_eq!(mult(&state), 2.0 * 2.0 * 2.0);
+
+        // Canio gains x1 for each face card destroyed, Yorick for every 23 discarded
+        state.jokers = vec![
+            JokerCard::new(JokerType::Canio(2)),
+            JokerCard::new(JokerType::Yorick(50)),
+        ];
+        assert_eq!(mult(&state), 2.0 * 3.0 * 3.0);
+    }
+
+    #[test]
+    fn test_glass_cards_break() {
+        let mut broken = 0;
+        for seed in 0..40 {
+            let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, seed);
+            state.select_blind().unwrap();
+
+            let id = state.in_hand[0].id;
+            state.in_hand[0].enhancement = Some(CardEnhancement::Glass);
+            state.select_card(id).unwrap();
+            let played = state.play_hand().unwrap();
+
+            let destroyed = state.deck.destroyed().iter().any(|card| card.id == id);
+            assert_eq!(destroyed, played.trace.get_destroyed() == vec![id]);
+            if destroyed {
+                broken += 1;
+                assert!(!state.deck.discard_pile().iter().any(|card| card.id == id));
+            }
+        }
+
+        // About 1 in 4
+        assert!((5..=15).contains(&broken), "{}", broken);
+    }
+}
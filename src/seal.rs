@@ -0,0 +1,137 @@
+use crate::{
+    card::{Card, CardSeal},
+    consumable::{Consumable, PlanetCard, TarotCard},
+    rng::RngStream,
+    GameState, PlayerMoney,
+};
+
+// Paid each time a card with a Gold seal scores
+pub const GOLD_SEAL_PAYOUT: PlayerMoney = 3;
+
+// Extra times a card's effects run because of its seal
+pub fn get_seal_retriggers(card: &Card) -> usize {
+    match card.seal {
+        Some(CardSeal::Red) => 1,
+        _ => 0,
+    }
+}
+
+impl GameState {
+    // Each Blue seal held when the round ends makes the planet for the last
+    // hand played, while there is room for it
+    pub(crate) fn trigger_blue_seals(&mut self) {
+        let Some(hand_type) = self.last_hand_played.clone() else {
+            return;
+        };
+
+        let blue_seals = self
+            .in_hand
+            .iter()
+            .filter(|card| card.seal == Some(CardSeal::Blue) && !card.is_debuffed)
+            .count();
+        for _ in 0..blue_seals {
+            let planet = PlanetCard::from_hand_type(&hand_type);
+            let _ = self.add_consumable(Consumable::Planet(planet));
+        }
+    }
+
+    // Each selected card with a Purple seal makes a tarot as it is discarded,
+    // while there is room for it
+    pub(crate) fn trigger_purple_seals(&mut self) {
+        let purple_seals = self
+            .selected_cards
+            .iter()
+            .filter(|card| card.seal == Some(CardSeal::Purple) && !card.is_debuffed)
+            .count();
+        for _ in 0..purple_seals {
+            let tarot = TarotCard::get_rand(self.rng.stream(RngStream::Consumable));
+            let _ = self.add_consumable(Consumable::Tarot(tarot));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        card::CardEnhancement,
+        hand::{HandRules, HandType},
+        new_test_game,
+        scoring::Chance,
+        stake::GameStake,
+        GameStartingDeck,
+    };
+
+    #[test]
+    fn test_gold_and_red_seals_score() {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 0);
+        state.select_blind().unwrap();
+        let mut card = state.in_hand[0].clone();
+        card.seal = Some(CardSeal::Gold);
+        let mut steel = state.in_hand[1].clone();
+        steel.enhancement = Some(CardEnhancement::Steel);
+        steel.seal = Some(CardSeal::Red);
+        state.in_hand = vec![steel];
+
+        let chips = card.rank.get_base_chips() as f64;
+        let (hand_type, played) = HandType::from_cards(vec![&card], &HandRules::default()).unwrap();
+        let trace = state.score_hand(&hand_type, &played, &mut Chance::Lowest);
+        assert_eq!(trace.total.chips, 5.0 + chips);
+        assert_eq!(trace.total.mult, 1.5 * 1.5);
+        assert_eq!(trace.get_money(), 3.0);
+
+        // A Red seal in place of the Gold one scores the card twice, but pays nothing
+        card.seal = Some(CardSeal::Red);
+        let (_, played) = HandType::from_cards(vec![&card], &HandRules::default()).unwrap();
+        let trace = state.score_hand(&hand_type, &played, &mut Chance::Lowest);
+        assert_eq!(trace.total.chips, 5.0 + 2.0 * chips);
+        assert_eq!(trace.get_money(), 0.0);
+    }
+
+    #[test]
+    fn test_blue_seals_make_last_hand_planet() {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 0);
+        state.select_blind().unwrap();
+        state.consumables.clear();
+        state.consumable_slots = 1;
+        for card in state.in_hand[1..3].iter_mut() {
+            card.seal = Some(CardSeal::Blue);
+        }
+
+        state.score = state.get_score_needed();
+        state.select_card(state.in_hand[0].id).unwrap();
+        state.play_hand().unwrap();
+
+        // Two Blue seals held, but only one slot free
+        assert_eq!(state.consumables.len(), 1);
+        assert_eq!(
+            state.consumables[0].consumable,
+            Consumable::Planet(PlanetCard::Pluto)
+        );
+    }
+
+    #[test]
+    fn test_purple_seals_make_tarots() {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 0);
+        state.select_blind().unwrap();
+        state.consumables.clear();
+        for card in state.in_hand[..3].iter_mut() {
+            card.seal = Some(CardSeal::Purple);
+        }
+        let ids = state.in_hand[..4]
+            .iter()
+            .map(|card| card.id)
+            .collect::<Vec<_>>();
+        for id in ids {
+            state.select_card(id).unwrap();
+        }
+
+        // Three Purple seals discarded, but only room for two tarots
+        state.discard_hand().unwrap();
+        assert_eq!(state.consumables.len(), state.consumable_slots as usize);
+        assert!(state
+            .consumables
+            .iter()
+            .all(|card| matches!(card.consumable, Consumable::Tarot(_))));
+    }
+}
@@ -0,0 +1,521 @@
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    booster::{BoosterPack, BoosterPackKind, OpenPack, PackCard},
+    card::{CardEdition, CardEnhancement, CardRank, CardSeal, CardSuit},
+    consumable::{Consumable, SpectralCard, TarotCard},
+    error::GameError,
+    joker::{JokerCard, JokerRarity, JokerType},
+    rng::RngStream,
+    voucher::{Voucher, BASE_VOUCHERS},
+    GamePhase, GameStartingDeck, GameState, PlayerMoney,
+};
+
+pub const BASE_REROLL_COST: u8 = 5;
+pub const BASE_SHOP_SLOTS: usize = 2;
+pub const SHOP_PACKS: usize = 2;
+
+#[derive(Clone, Debug)]
+pub enum SaleCard {
+    Joker(JokerCard),
+    Consumable(Consumable),
+}
+
+impl SaleCard {
+    pub fn get_cost(&self) -> u8 {
+        match self {
+            SaleCard::Joker(joker) => joker.joker.get_cost(),
+            SaleCard::Consumable(consumable) => consumable.get_cost(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct GameShopState {
+    pub cards: Vec<SaleCard>,
+
+    pub vouchers: Vec<Voucher>,
+    pub packs: Vec<BoosterPack>,
+    pub reroll_cost: u8,
+}
+
+impl GameState {
+    // Price after Clearance Sale / Liquidation, never below $1
+    pub fn get_price(&self, base_cost: u8) -> PlayerMoney {
+        let discount = if self.vouchers.contains(&Voucher::Liquidation) {
+            50
+        } else if self.vouchers.contains(&Voucher::ClearanceSale) {
+            25
+        } else {
+            0
+        };
+
+        let cost = (base_cost as f32 * (100 - discount) as f32 / 100.0).round() as PlayerMoney;
+        cost.max(1)
+    }
+
+    pub fn get_shop_slots(&self) -> usize {
+        BASE_SHOP_SLOTS
+            + self
+                .vouchers
+                .iter()
+                .filter(|voucher| matches!(voucher, Voucher::Overstock | Voucher::OverstockPlus))
+                .count()
+    }
+
+    fn get_base_reroll_cost(&self) -> u8 {
+        let discount = self
+            .vouchers
+            .iter()
+            .filter(|voucher| matches!(voucher, Voucher::RerollSurplus | Voucher::RerollGlut))
+            .count() as u8
+            * 2;
+
+        BASE_REROLL_COST.saturating_sub(discount)
+    }
+
+    fn spend(&mut self, cost: PlayerMoney) -> Result<(), GameError> {
+        if self.money < cost {
+            return Err(GameError::NotEnoughMoney {
+                cost,
+                money: self.money,
+            });
+        }
+
+        self.money -= cost;
+        Ok(())
+    }
+
+    pub(crate) fn expect_shop(&self) -> Result<(), GameError> {
+        self.expect_phase(GamePhase::Shop)?;
+
+        if self.open_pack.is_some() {
+            return Err(GameError::PackOpen);
+        }
+
+        Ok(())
+    }
+
+    // Hone and Glow Up make editions two and four times as likely
+    pub fn get_edition_rate(&self) -> f64 {
+        if self.vouchers.contains(&Voucher::GlowUp) {
+            4.0
+        } else if self.vouchers.contains(&Voucher::Hone) {
+            2.0
+        } else {
+            1.0
+        }
+    }
+
+    fn roll_joker_card(&mut self, stream: RngStream) -> JokerCard {
+        let mut joker = JokerCard::new(self.roll_joker(stream));
+        let rate = self.get_edition_rate();
+        if let Some(edition) = CardEdition::roll(self.rng.stream(stream), rate, true) {
+            joker.edition = edition;
+        }
+        joker
+    }
+
+    // Rarity is 70% common, 25% uncommon, 5% rare; jokers already owned are skipped
+    pub(crate) fn roll_joker(&mut self, stream: RngStream) -> JokerType {
+        let rng = self.rng.stream(stream);
+        let rarity = match rng.gen_range(0..100) {
+            0..=69 => JokerRarity::Common,
+            70..=94 => JokerRarity::Uncommon,
+            _ => JokerRarity::Rare,
+        };
+
+        for _ in 0..10 {
+            let joker = JokerType::get_rand(rarity.clone(), rng).unwrap();
+            if !self.jokers.iter().any(|owned| owned.joker.is_same(&joker)) {
+                return joker;
+            }
+        }
+
+        JokerType::Joker
+    }
+
+    fn roll_shop_card(&mut self) -> SaleCard {
+        let tarot_weight = if self.vouchers.contains(&Voucher::TarotTycoon) {
+            32.0
+        } else if self.vouchers.contains(&Voucher::TarotMerchant) {
+            9.6
+        } else {
+            4.0
+        };
+
+        let planet_weight = if self.vouchers.contains(&Voucher::PlanetTycoon) {
+            32.0
+        } else if self.vouchers.contains(&Voucher::PlanetMerchant) {
+            9.6
+        } else {
+            4.0
+        };
+
+        let spectral_weight = match self.starting_deck {
+            GameStartingDeck::Ghost => 2.0,
+            _ => 0.0,
+        };
+
+        let weights = [20.0, tarot_weight, planet_weight, spectral_weight];
+        let rng = self.rng.stream(RngStream::Shop);
+        let kind = (0..weights.len())
+            .collect::<Vec<_>>()
+            .choose_weighted(rng, |idx| weights[*idx])
+            .copied()
+            .unwrap();
+
+        match kind {
+            0 => SaleCard::Joker(self.roll_joker_card(RngStream::Shop)),
+            1 => SaleCard::Consumable(Consumable::Tarot(TarotCard::get_rand(rng))),
+            2 => SaleCard::Consumable(Consumable::Planet(self.roll_planet(RngStream::Shop))),
+            _ => SaleCard::Consumable(Consumable::Spectral(SpectralCard::get_rand(rng))),
+        }
+    }
+
+    pub(crate) fn roll_voucher(&mut self) -> Option<Voucher> {
+        let available = BASE_VOUCHERS
+            .iter()
+            .filter_map(|voucher| {
+                if !self.vouchers.contains(voucher) {
+                    return Some(voucher.clone());
+                }
+
+                voucher
+                    .get_upgrade()
+                    .filter(|upgrade| !self.vouchers.contains(upgrade))
+            })
+            .collect::<Vec<_>>();
+
+        available.choose(self.rng.stream(RngStream::Shop)).cloned()
+    }
+
+    fn roll_shop_cards(&mut self) {
+        self.shop.cards = (0..self.get_shop_slots())
+            .map(|_| self.roll_shop_card())
+            .collect();
+    }
+
+    /// Fills the shop for a new visit. The voucher only changes at the start of
+    /// each ante.
+    pub(crate) fn restock_shop(&mut self, new_ante: bool) {
+        self.roll_shop_cards();
+
+        self.shop.packs = (0..SHOP_PACKS)
+            .map(|_| BoosterPack::get_rand(self.rng.stream(RngStream::Shop)))
+            .collect();
+
+        self.shop.reroll_cost = self.get_base_reroll_cost();
+
+        if new_ante {
+            self.shop.vouchers = self.roll_voucher().into_iter().collect();
+        }
+    }
+
+    pub fn reroll_shop(&mut self) -> Result<(), GameError> {
+        self.expect_shop()?;
+        self.spend(self.shop.reroll_cost as PlayerMoney)?;
+
+        self.shop.reroll_cost += 1;
+        self.roll_shop_cards();
+        Ok(())
+    }
+
+    pub fn buy_card(&mut self, index: usize) -> Result<(), GameError> {
+        self.expect_shop()?;
+
+        let card = self
+            .shop
+            .cards
+            .get(index)
+            .ok_or(GameError::InvalidIndex(index))?;
+
+        match card {
+            SaleCard::Joker(joker) if !self.has_joker_slot(joker) => {
+                return Err(GameError::JokerSlotsFull);
+            }
+            SaleCard::Consumable(_) if self.consumables.len() >= self.get_consumable_slots() => {
+                return Err(GameError::ConsumableSlotsFull);
+            }
+            _ => {}
+        }
+
+        self.spend(self.get_price(card.get_cost()))?;
+
+        match self.shop.cards.remove(index) {
+            SaleCard::Joker(joker) => self.add_joker(joker),
+            SaleCard::Consumable(consumable) => self.add_consumable(consumable),
+        }
+    }
+
+    pub fn buy_voucher(&mut self, index: usize) -> Result<(), GameError> {
+        self.expect_shop()?;
+
+        let voucher = self
+            .shop
+            .vouchers
+            .get(index)
+            .ok_or(GameError::InvalidIndex(index))?;
+
+        self.spend(self.get_price(voucher.get_cost()))?;
+
+        let voucher = self.shop.vouchers.remove(index);
+        self.add_voucher(voucher);
+        Ok(())
+    }
+
+    pub fn open_pack(&mut self, index: usize) -> Result<(), GameError> {
+        self.expect_shop()?;
+
+        let pack = self
+            .shop
+            .packs
+            .get(index)
+            .ok_or(GameError::InvalidIndex(index))?;
+
+        self.spend(self.get_price(pack.get_cost()))?;
+
+        let pack = self.shop.packs.remove(index);
+        let cards = (0..pack.get_size())
+            .map(|_| self.roll_pack_card(&pack))
+            .collect();
+
+        self.open_pack = Some(OpenPack {
+            choices_left: pack.get_choices(),
+            pack,
+            cards,
+        });
+        Ok(())
+    }
+
+    fn roll_pack_card(&mut self, pack: &BoosterPack) -> PackCard {
+        match pack.get_kind() {
+            BoosterPackKind::Standard => {
+                // Playing cards get editions twice as often as shop jokers
+                let rate = 2.0 * self.get_edition_rate();
+                let rng = self.rng.stream(RngStream::Pack);
+                let rank = CardRank::get_rand(rng);
+                let suit = CardSuit::get_rand(rng);
+                let enhancement = rng.gen_bool(0.4).then(|| CardEnhancement::get_rand(rng));
+                let edition = CardEdition::roll(rng, rate, false);
+                let seal = rng.gen_bool(0.2).then(|| CardSeal::get_rand(rng));
+
+                let mut card = self.create_card(rank, suit);
+                card.enhancement = enhancement;
+                card.edition = edition;
+                card.seal = seal;
+                PackCard::Card(card)
+            }
+            BoosterPackKind::Arcana => {
+                let tarot = TarotCard::get_rand(self.rng.stream(RngStream::Pack));
+                PackCard::Consumable(Consumable::Tarot(tarot))
+            }
+            BoosterPackKind::Celestial => {
+                let planet = self.roll_planet(RngStream::Pack);
+                PackCard::Consumable(Consumable::Planet(planet))
+            }
+            BoosterPackKind::Buffoon => PackCard::Joker(self.roll_joker_card(RngStream::Pack)),
+            BoosterPackKind::Spectral => {
+                let spectral = SpectralCard::get_rand(self.rng.stream(RngStream::Pack));
+                PackCard::Consumable(Consumable::Spectral(spectral))
+            }
+        }
+    }
+
+    pub fn can_pick_pack_card(&self, card: &PackCard) -> bool {
+        match card {
+            PackCard::Card(_) => true,
+            PackCard::Joker(joker) => self.has_joker_slot(joker),
+            // Planets are used straight away
+            PackCard::Consumable(Consumable::Planet(_)) => true,
+            PackCard::Consumable(_) => self.consumables.len() < self.get_consumable_slots(),
+        }
+    }
+
+    /// Takes a card from the open pack. Playing cards join the deck, planets
+    /// level up their hand immediately, and everything else is kept.
+    pub fn pick_pack_card(&mut self, index: usize) -> Result<(), GameError> {
+        let open_pack = self.open_pack.as_ref().ok_or(GameError::PackNotOpen)?;
+        let card = open_pack
+            .cards
+            .get(index)
+            .ok_or(GameError::InvalidIndex(index))?;
+
+        match card {
+            PackCard::Joker(_) if !self.can_pick_pack_card(card) => {
+                return Err(GameError::JokerSlotsFull);
+            }
+            PackCard::Consumable(_) if !self.can_pick_pack_card(card) => {
+                return Err(GameError::ConsumableSlotsFull);
+            }
+            _ => {}
+        }
+
+        let open_pack = self.open_pack.as_mut().unwrap();
+        let card = open_pack.cards.remove(index);
+        open_pack.choices_left -= 1;
+
+        if open_pack.choices_left == 0 || open_pack.cards.is_empty() {
+            self.open_pack = None;
+        }
+
+        match card {
+            PackCard::Card(card) => {
+                self.deck.add_card(card);
+                self.deck.shuffle(self.rng.stream(RngStream::Shuffle));
+            }
+            PackCard::Joker(joker) => self.add_joker(joker)?,
+            PackCard::Consumable(Consumable::Planet(planet)) => {
+                self.level_up_hand(planet.get_hand_type());
+            }
+            PackCard::Consumable(consumable) => self.add_consumable(consumable)?,
+        }
+
+        Ok(())
+    }
+
+    pub fn skip_pack(&mut self) -> Result<(), GameError> {
+        self.open_pack.take().ok_or(GameError::PackNotOpen)?;
+        Ok(())
+    }
+
+    pub fn sell_joker(&mut self, index: usize) -> Result<(), GameError> {
+        self.expect_running()?;
+
+        if index >= self.jokers.len() {
+            return Err(GameError::InvalidIndex(index));
+        }
+
+        let joker = self.jokers.remove(index);
+        self.money += joker.value as PlayerMoney;
+        Ok(())
+    }
+
+    pub fn sell_consumable(&mut self, index: usize) -> Result<(), GameError> {
+        self.expect_running()?;
+
+        if index >= self.consumables.len() {
+            return Err(GameError::InvalidIndex(index));
+        }
+
+        let consumable = self.consumables.remove(index);
+        self.money += consumable.consumable.get_sell_value() as PlayerMoney;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{booster::BOOSTER_PACKS, new_test_game, stake::GameStake};
+
+    // Wins the first blind and cashes out, leaving the game in the shop
+    fn new_shop() -> GameState {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 0);
+        state.select_blind().unwrap();
+        state.score = state.get_score_needed();
+        state.select_card(state.in_hand[0].id).unwrap();
+        state.play_hand().unwrap();
+        state.collect_cash_out().unwrap();
+        state.money = 100;
+        state
+    }
+
+    #[test]
+    fn test_buy_and_reroll() {
+        let mut state = new_shop();
+        let cost = state.get_price(state.shop.cards[0].get_cost());
+        let owned = state.jokers.len() + state.consumables.len();
+        state.buy_card(0).unwrap();
+        assert_eq!(state.money, 100 - cost);
+        assert_eq!(state.jokers.len() + state.consumables.len(), owned + 1);
+        assert_eq!(state.shop.cards.len(), 1);
+
+        // Each reroll costs $1 more than the last, and refills every slot
+        state.reroll_shop().unwrap();
+        state.reroll_shop().unwrap();
+        assert_eq!(state.money, 100 - cost - 5 - 6);
+        assert_eq!(state.shop.reroll_cost, 7);
+        assert_eq!(state.shop.cards.len(), 2);
+
+        state.money = 0;
+        assert!(matches!(
+            state.buy_card(0),
+            Err(GameError::NotEnoughMoney { .. })
+        ));
+        assert_eq!(state.buy_card(2), Err(GameError::InvalidIndex(2)));
+    }
+
+    #[test]
+    fn test_voucher_discounts() {
+        let mut state = new_shop();
+        state.add_voucher(Voucher::ClearanceSale);
+        assert_eq!(state.get_price(8), 6);
+        state.add_voucher(Voucher::Liquidation);
+        assert_eq!(state.get_price(8), 4);
+        assert_eq!(state.get_price(1), 1);
+
+        state.add_voucher(Voucher::Overstock);
+        assert_eq!(state.get_shop_slots(), 3);
+    }
+
+    #[test]
+    fn test_open_every_pack() {
+        for pack in BOOSTER_PACKS.iter() {
+            let mut state = new_shop();
+            state.shop.packs = vec![pack.clone()];
+            state.open_pack(0).unwrap();
+            assert_eq!(state.money, 100 - pack.get_cost() as PlayerMoney);
+
+            let open_pack = state.open_pack.clone().unwrap();
+            assert_eq!(open_pack.cards.len(), pack.get_size() as usize);
+            assert_eq!(open_pack.choices_left, pack.get_choices());
+            for card in open_pack.cards.iter() {
+                let kind = match card {
+                    PackCard::Card(_) => BoosterPackKind::Standard,
+                    PackCard::Joker(_) => BoosterPackKind::Buffoon,
+                    PackCard::Consumable(Consumable::Tarot(_)) => BoosterPackKind::Arcana,
+                    PackCard::Consumable(Consumable::Planet(_)) => BoosterPackKind::Celestial,
+                    PackCard::Consumable(Consumable::Spectral(_)) => BoosterPackKind::Spectral,
+                };
+                assert_eq!(kind, pack.get_kind());
+            }
+
+            // The pack closes once every choice is used
+            for _ in 0..pack.get_choices() {
+                assert!(state.open_pack.is_some());
+                state.pick_pack_card(0).unwrap();
+            }
+            assert!(state.open_pack.is_none());
+            assert_eq!(state.pick_pack_card(0), Err(GameError::PackNotOpen));
+        }
+    }
+
+    #[test]
+    fn test_edition_rates() {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 0);
+        assert_eq!(state.get_edition_rate(), 1.0);
+
+        // One card in 25 gets an edition at the base rate, doubled for packs
+        let count_editions = |state: &mut GameState| {
+            (0..20_000)
+                .filter(|_| match state.roll_pack_card(&BoosterPack::Standard) {
+                    PackCard::Card(card) => card.edition.is_some(),
+                    _ => unreachable!(),
+                })
+                .count()
+        };
+        let editions = count_editions(&mut state);
+        assert!((1400..1800).contains(&editions), "{}", editions);
+
+        state.add_voucher(Voucher::Hone);
+        assert_eq!(state.get_edition_rate(), 2.0);
+        let editions = count_editions(&mut state);
+        assert!((3000..3400).contains(&editions), "{}", editions);
+
+        state.add_voucher(Voucher::GlowUp);
+        assert_eq!(state.get_edition_rate(), 4.0);
+        let editions = count_editions(&mut state);
+        assert!((6200..6600).contains(&editions), "{}", editions);
+    }
+}
@@ -1,3 +1,5 @@
+// Not called yet, but kept for wasm entry points such as `greet` to use
+#[allow(dead_code)]
 pub fn set_panic_hook() {
     // When the `console_error_panic_hook` feature is enabled, we can call the
     // `set_panic_hook` function at least once during initialization, and then
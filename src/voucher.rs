@@ -1,5 +1,7 @@
 // https://balatrogame.fandom.com/wiki/Vouchers
 
+use crate::GameState;
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Voucher {
     Overstock,
@@ -88,3 +90,96 @@ pub const UPGRADED_VOUCHERS: [Voucher; 16] = [
     Voucher::Retcon,
     Voucher::Palette,
 ];
+
+impl Voucher {
+    pub fn get_cost(&self) -> u8 {
+        10
+    }
+
+    // Upgraded vouchers only show up once their base voucher is owned
+    pub fn get_upgrade(&self) -> Option<Voucher> {
+        BASE_VOUCHERS
+            .iter()
+            .position(|voucher| voucher == self)
+            .map(|idx| UPGRADED_VOUCHERS[idx].clone())
+    }
+}
+
+impl GameState {
+    pub fn add_voucher(&mut self, voucher: Voucher) {
+        match voucher {
+            Voucher::CrystalBall => self.consumable_slots += 1,
+            Voucher::Grabber | Voucher::NachoTong => {
+                self.hands_total += 1;
+                self.hands += 1;
+            }
+            Voucher::Wasteful | Voucher::Recyclomancy => {
+                self.discards_total += 1;
+                self.discards += 1;
+            }
+            Voucher::SeedMoney => self.interest_cap = self.interest_cap.max(10),
+            Voucher::MoneyTree => self.interest_cap = self.interest_cap.max(20),
+            Voucher::Antimatter => self.joker_slots += 1,
+            Voucher::Hieroglyph => {
+                self.ante = self.ante.saturating_sub(1);
+                self.hands_total = self.hands_total.saturating_sub(1);
+            }
+            Voucher::Petroglyph => {
+                self.ante = self.ante.saturating_sub(1);
+                self.discards_total = self.discards_total.saturating_sub(1);
+            }
+            Voucher::PaintBrush | Voucher::Palette => self.hand_size += 1,
+            Voucher::RerollSurplus | Voucher::RerollGlut => {
+                self.shop.reroll_cost = self.shop.reroll_cost.saturating_sub(2);
+            }
+            // The rest change shop odds and prices, and are checked where those are rolled
+            _ => {}
+        }
+
+        self.vouchers.push(voucher);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{new_test_game, stake::GameStake, GameStartingDeck};
+
+    #[test]
+    fn test_upgrades() {
+        for (base, upgraded) in BASE_VOUCHERS.iter().zip(UPGRADED_VOUCHERS.iter()) {
+            assert_eq!(base.get_upgrade(), Some(upgraded.clone()));
+            assert_eq!(upgraded.get_upgrade(), None);
+        }
+    }
+
+    #[test]
+    fn test_voucher_effects() {
+        let mut state = new_test_game(GameStartingDeck::Red, GameStake::White, 0);
+        let before = state.clone();
+
+        state.add_voucher(Voucher::Grabber);
+        state.add_voucher(Voucher::Wasteful);
+        state.add_voucher(Voucher::CrystalBall);
+        state.add_voucher(Voucher::PaintBrush);
+        state.add_voucher(Voucher::Antimatter);
+        assert_eq!(state.hands_total, before.hands_total + 1);
+        assert_eq!(state.hands, before.hands + 1);
+        assert_eq!(state.discards_total, before.discards_total + 1);
+        assert_eq!(state.discards, before.discards + 1);
+        assert_eq!(state.consumable_slots, before.consumable_slots + 1);
+        assert_eq!(state.hand_size, before.hand_size + 1);
+        assert_eq!(state.joker_slots, before.joker_slots + 1);
+        assert_eq!(state.vouchers.len(), before.vouchers.len() + 5);
+
+        // Hieroglyph sets the ante back at the cost of a hand each round
+        state.ante = 3;
+        state.add_voucher(Voucher::Hieroglyph);
+        assert_eq!(state.ante, 2);
+        assert_eq!(state.hands_total, before.hands_total);
+
+        state.shop.reroll_cost = 5;
+        state.add_voucher(Voucher::RerollSurplus);
+        assert_eq!(state.shop.reroll_cost, 3);
+    }
+}